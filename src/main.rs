@@ -17,6 +17,18 @@ macro_rules! solution {
     }};
 }
 
+macro_rules! explanation {
+    ($day:tt) => {{
+        let day = stringify!($day);
+        let input_file = Path::new("resources").join(day).join("input.txt");
+        if !input_file.exists() {
+            let error_string = format!("File {} doesn't exist", input_file.to_str().unwrap());
+            return Err(error_string);
+        }
+        return solutions::$day::explain(input_file.to_str().unwrap());
+    }};
+}
+
 const ANSI_BOLD: &str = "\x1b[1m";
 const ANSI_RESET: &str = "\x1b[0m";
 fn main() {
@@ -47,6 +59,9 @@ enum ExecutionTarget {
     RunSingleDay(i32),
     Help,
     Benchmark(i32),
+    Interactive,
+    CheatHistogram(usize, usize),
+    Explain(i32),
 }
 
 fn execute_ci_options(options: CIOptions) {
@@ -94,6 +109,30 @@ fn execute_ci_options(options: CIOptions) {
                 println!("|  {:0>2} | {: >12.2?} | {: >12.2?} | {: >12.2?} |", day, avg.as_micros(), min.as_micros(), max.as_micros());
             }
         }
+        ExecutionTarget::Interactive => {
+            let input_file = Path::new("resources").join("day15").join("input.txt");
+            if !input_file.exists() {
+                eprintln!("File {} doesn't exist", input_file.to_str().unwrap());
+                return;
+            }
+            solutions::day15::play(input_file.to_str().unwrap());
+        }
+        ExecutionTarget::CheatHistogram(cheat_length, min_saving) => {
+            let input_file = Path::new("resources").join("day20").join("input.txt");
+            if !input_file.exists() {
+                eprintln!("File {} doesn't exist", input_file.to_str().unwrap());
+                return;
+            }
+            solutions::day20::print_cheat_histogram(
+                input_file.to_str().unwrap(),
+                cheat_length,
+                min_saving,
+            );
+        }
+        ExecutionTarget::Explain(day) => match explain_day(day) {
+            Ok(explanation) => println!("{}", explanation),
+            Err(reason) => eprintln!("{}", reason),
+        },
         ExecutionTarget::Help => {
             println!("Usage:");
             println!("  aoc2024 [OPTION]");
@@ -102,6 +141,9 @@ fn execute_ci_options(options: CIOptions) {
             println!("  -d, -day  <n>        # Solves a specific day n where n is in [1..25]");
             println!("  -a, -all             # Solves all days");
             println!("  -b, -benchmark       # Runs the internal benchmark");
+            println!("  -i, -interactive     # Steers the robot of day 15 with the arrow keys");
+            println!("  -c, -cheats N M      # Lists the day 20 cheats up to length N saving at least M");
+            println!("  -e, -explain <n>     # Explains the solution of day n in detail");
             println!("  -h, -help            # Prints this page   ");
         }
     }
@@ -136,6 +178,20 @@ fn parse_options(args: &Vec<String>) -> CIOptions {
                 }
                 None => {}
             },
+            "-i" | "-interactive" => {
+                execution_target = ExecutionTarget::Interactive;
+            }
+            "-c" | "-cheats" => {
+                let cheat_length = args.next().map(|n| n.parse::<usize>().unwrap()).unwrap_or(2);
+                let min_saving = args.next().map(|m| m.parse::<usize>().unwrap()).unwrap_or(1);
+                execution_target = ExecutionTarget::CheatHistogram(cheat_length, min_saving);
+            }
+            "-e" | "-explain" => {
+                if let Some(day) = args.next() {
+                    let d = day.parse::<i32>().unwrap();
+                    execution_target = ExecutionTarget::Explain(d);
+                }
+            }
             "-h" | "-help" | "?" => {
                 execution_target = ExecutionTarget::Help;
             }
@@ -181,3 +237,28 @@ fn solve_day(day: i32) -> Result<(String, String), String> {
     };
     solution
 }
+
+// Days listed here describe their solution beyond the two answers
+fn explain_day(day: i32) -> Result<String, String> {
+    match day {
+        2 => explanation!(day02),
+        3 => explanation!(day03),
+        4 => explanation!(day04),
+        5 => explanation!(day05),
+        6 => explanation!(day06),
+        7 => explanation!(day07),
+        8 => explanation!(day08),
+        9 => explanation!(day09),
+        10 => explanation!(day10),
+        11 => explanation!(day11),
+        12 => explanation!(day12),
+        14 => explanation!(day14),
+        16 => explanation!(day16),
+        19 => explanation!(day19),
+        21 => explanation!(day21),
+        22 => explanation!(day22),
+        23 => explanation!(day23),
+        25 => explanation!(day25),
+        _ => Err(format!("There is no explanation for day {}", day)),
+    }
+}
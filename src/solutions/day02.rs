@@ -1,3 +1,4 @@
+use std::fmt::{Display, Formatter};
 use std::fs;
 
 pub fn solve(puzzle_file_path: &str) -> (String, String) {
@@ -7,7 +8,21 @@ pub fn solve(puzzle_file_path: &str) -> (String, String) {
     (solution_one, solution_two)
 }
 
-fn solve_part_one(puzzle: &Vec<Vec<i32>>) -> String {
+// Lists why every report is unsafe, first without and then with the problem dampener
+pub fn explain(puzzle_file_path: &str) -> Result<String, String> {
+    let puzzle = parse_file(puzzle_file_path);
+    let damped = SafetyRules {
+        tolerance: 1,
+        ..SafetyRules::default()
+    };
+    Ok(format!(
+        "Unsafe reports:\n{}\nUnsafe reports with the problem dampener:\n{}",
+        format_diagnostics(&puzzle, &SafetyRules::default()),
+        format_diagnostics(&puzzle, &damped)
+    ))
+}
+
+fn solve_part_one(puzzle: &[Vec<i32>]) -> String {
     let safe_reports = puzzle
         .iter()
         .filter(|report| is_safe_report(report))
@@ -15,7 +30,7 @@ fn solve_part_one(puzzle: &Vec<Vec<i32>>) -> String {
     safe_reports.to_string()
 }
 
-fn solve_part_two(puzzle: &Vec<Vec<i32>>) -> String {
+fn solve_part_two(puzzle: &[Vec<i32>]) -> String {
     let safe_reports = puzzle
         .iter()
         .filter(|report| is_safe_report_damped(report))
@@ -23,48 +38,183 @@ fn solve_part_two(puzzle: &Vec<Vec<i32>>) -> String {
     safe_reports.to_string()
 }
 
-fn is_safe_report(report: &Vec<i32>) -> bool {
-    let is_report_ascending = report[0] < report[1];
-    for i in 0..report.len() - 1 {
-        let p1 = report[i];
-        let p2 = report[i + 1];
-        let is_pair_ascending = p1 < p2;
-        if is_report_ascending != is_pair_ascending {
-            return false;
-        }
-        let diff = (p1 - p2).abs();
-        if diff < 1 || diff > 3 {
-            return false;
+// Neighbouring levels have to differ by `min_step` to `max_step` in the same direction across
+// the report, after removing at most `tolerance` levels
+#[derive(Debug, Clone, Copy)]
+struct SafetyRules {
+    min_step: i32,
+    max_step: i32,
+    tolerance: usize,
+}
+
+impl Default for SafetyRules {
+    fn default() -> Self {
+        SafetyRules {
+            min_step: 1,
+            max_step: 3,
+            tolerance: 0,
         }
     }
-    true
 }
 
-fn is_safe_report_damped(report: &Vec<i32>) -> bool {
-    if is_safe_report(report) {
-        return true;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RuleViolation {
+    StepTooSmall(i32),
+    StepTooLarge(i32),
+    DirectionChange { increasing: bool },
+}
+
+// The level at `index` can't follow the level at `previous_index`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Diagnosis {
+    previous_index: usize,
+    index: usize,
+    violation: RuleViolation,
+}
+
+impl SafetyRules {
+    // `direction` is 1 for increasing and -1 for decreasing reports
+    fn check_step(&self, from: i32, to: i32, direction: i32) -> Result<(), RuleViolation> {
+        let step = (to - from) * direction;
+        if step < 0 {
+            Err(RuleViolation::DirectionChange {
+                increasing: direction > 0,
+            })
+        } else if step < self.min_step {
+            Err(RuleViolation::StepTooSmall(step))
+        } else if step > self.max_step {
+            Err(RuleViolation::StepTooLarge(step))
+        } else {
+            Ok(())
+        }
     }
-    for drop_index in 0..report.len() {
-        let mut reduced_report = report.clone();
-        reduced_report.remove(drop_index);
-        if is_safe_report(&reduced_report) {
-            return true;
+
+    // Returns the index of the furthest level that can be kept and if the report is safe.
+    // `removals[i]` is the least number of levels to remove so that the kept levels up to i
+    // are valid. Only the last `tolerance` + 1 levels can precede a kept level, which makes
+    // the check linear for a fixed tolerance.
+    fn check_direction(&self, report: &[i32], direction: i32) -> (usize, bool) {
+        let k = self.tolerance;
+        let mut removals: Vec<Option<usize>> = vec![None; report.len()];
+        for i in 0..report.len() {
+            let mut best = (i <= k).then_some(i);
+            for j in i.saturating_sub(k + 1)..i {
+                if let Some(removed) = removals[j] {
+                    let removed = removed + i - j - 1;
+                    let is_valid = self.check_step(report[j], report[i], direction).is_ok();
+                    if is_valid && removed <= k && best.is_none_or(|b| removed < b) {
+                        best = Some(removed);
+                    }
+                }
+            }
+            removals[i] = best;
+        }
+
+        let is_safe = report.len() <= k
+            || removals
+                .iter()
+                .enumerate()
+                .any(|(i, removed)| removed.is_some_and(|r| r + report.len() - 1 - i <= k));
+        let furthest = removals.iter().rposition(|r| r.is_some()).unwrap_or(0);
+        (furthest, is_safe)
+    }
+
+    fn is_safe(&self, report: &[i32]) -> bool {
+        self.check_direction(report, 1).1 || self.check_direction(report, -1).1
+    }
+
+    // Explains why a report is unsafe with the direction that got further through the report,
+    // the one of the first two levels if both got equally far
+    fn diagnose(&self, report: &[i32]) -> Option<Diagnosis> {
+        let (increasing, increasing_safe) = self.check_direction(report, 1);
+        let (decreasing, decreasing_safe) = self.check_direction(report, -1);
+        if increasing_safe || decreasing_safe {
+            return None;
+        }
+        let starts_increasing = report.len() < 2 || report[0] <= report[1];
+        let (furthest, direction) = match increasing.cmp(&decreasing) {
+            std::cmp::Ordering::Greater => (increasing, 1),
+            std::cmp::Ordering::Less => (decreasing, -1),
+            _ if starts_increasing => (increasing, 1),
+            _ => (decreasing, -1),
+        };
+        let violation = self
+            .check_step(report[furthest], report[furthest + 1], direction)
+            .err()
+            // The next level would fit, but too many levels had to be removed to get here
+            .unwrap_or(RuleViolation::DirectionChange {
+                increasing: direction > 0,
+            });
+        Some(Diagnosis {
+            previous_index: furthest,
+            index: furthest + 1,
+            violation,
+        })
+    }
+}
+
+impl Display for RuleViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleViolation::StepTooSmall(step) => write!(f, "changes by only {}", step),
+            RuleViolation::StepTooLarge(step) => write!(f, "changes by {}", step),
+            RuleViolation::DirectionChange { increasing: true } => {
+                write!(f, "decreases in an increasing report")
+            }
+            RuleViolation::DirectionChange { increasing: false } => {
+                write!(f, "increases in a decreasing report")
+            }
         }
     }
-    false
+}
+
+// One line per unsafe report with 1-based numbers for reports and levels, e.g.
+// `Report 2: level 3 (7) changes by 5 after level 2 (2)`
+fn format_diagnostics(puzzle: &[Vec<i32>], rules: &SafetyRules) -> String {
+    puzzle
+        .iter()
+        .enumerate()
+        .filter_map(|(number, report)| {
+            let diagnosis = rules.diagnose(report)?;
+            Some(format!(
+                "Report {}: level {} ({}) {} after level {} ({})",
+                number + 1,
+                diagnosis.index + 1,
+                report[diagnosis.index],
+                diagnosis.violation,
+                diagnosis.previous_index + 1,
+                report[diagnosis.previous_index]
+            ))
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn is_safe_report(report: &[i32]) -> bool {
+    SafetyRules::default().is_safe(report)
+}
+
+fn is_safe_report_damped(report: &[i32]) -> bool {
+    let rules = SafetyRules {
+        tolerance: 1,
+        ..SafetyRules::default()
+    };
+    rules.is_safe(report)
 }
 
 fn parse_file(file_path: &str) -> Vec<Vec<i32>> {
-    let lines = fs::read_to_string(file_path)
-        .unwrap()
+    parse_reports(&fs::read_to_string(file_path).unwrap())
+}
+
+fn parse_reports(input: &str) -> Vec<Vec<i32>> {
+    input
         .lines()
         .map(|line| {
             line.split_whitespace()
                 .map(|value| value.parse::<i32>().unwrap())
                 .collect()
         })
-        .collect::<Vec<Vec<i32>>>();
-    lines
+        .collect::<Vec<Vec<i32>>>()
 }
 
 #[cfg(test)]
@@ -84,4 +234,84 @@ mod tests {
         let solution_one = solve_part_two(&puzzle);
         assert_eq!(solution_one, "4");
     }
+
+    const EXAMPLE: &str = "7 6 4 2 1\n1 2 7 8 9\n9 7 6 2 1\n1 3 2 4 5\n8 6 4 4 1\n1 3 6 7 9";
+
+    #[test]
+    fn test_diagnostics() {
+        let puzzle = parse_reports(EXAMPLE);
+        assert_eq!(solve_part_one(&puzzle), "2");
+        assert_eq!(solve_part_two(&puzzle), "4");
+
+        assert_eq!(
+            format_diagnostics(&puzzle, &SafetyRules::default()),
+            "Report 2: level 3 (7) changes by 5 after level 2 (2)\n\
+             Report 3: level 4 (2) changes by 4 after level 3 (6)\n\
+             Report 4: level 3 (2) decreases in an increasing report after level 2 (3)\n\
+             Report 5: level 4 (4) changes by only 0 after level 3 (4)"
+        );
+        let damped = SafetyRules {
+            tolerance: 1,
+            ..SafetyRules::default()
+        };
+        assert_eq!(
+            format_diagnostics(&puzzle, &damped),
+            "Report 2: level 3 (7) changes by 5 after level 2 (2)\n\
+             Report 3: level 4 (2) changes by 4 after level 3 (6)"
+        );
+    }
+
+    #[test]
+    fn test_tolerance() {
+        // Compare with removing every combination of up to two levels
+        fn brute_force(report: &[i32], rules: &SafetyRules) -> bool {
+            let strict = SafetyRules {
+                tolerance: 0,
+                ..*rules
+            };
+            let n = report.len();
+            (0..=n).any(|a| {
+                (a..=n).any(|b| {
+                    let removed = (a < n) as usize + (b < n && b != a) as usize;
+                    let kept = (0..n)
+                        .filter(|i| *i != a && *i != b)
+                        .map(|i| report[i])
+                        .collect::<Vec<i32>>();
+                    removed <= rules.tolerance && strict.is_safe(&kept)
+                })
+            })
+        }
+
+        let rules = SafetyRules {
+            min_step: 2,
+            max_step: 4,
+            tolerance: 2,
+        };
+        // Every report of five levels between 0 and 6
+        for combination in 0..7_i32.pow(5) {
+            let report = (0..5)
+                .map(|i| combination / 7_i32.pow(i) % 7)
+                .collect::<Vec<i32>>();
+            assert_eq!(
+                rules.is_safe(&report),
+                brute_force(&report, &rules),
+                "{:?}",
+                report
+            );
+            let damped = SafetyRules {
+                tolerance: 1,
+                ..rules
+            };
+            assert_eq!(
+                damped.is_safe(&report),
+                brute_force(&report, &damped),
+                "{:?}",
+                report
+            );
+        }
+
+        assert!(rules.is_safe(&[1, 20, 30, 3, 5]));
+        assert!(!rules.is_safe(&[1, 20, 30, 40, 3, 5]));
+        assert!(rules.is_safe(&[]));
+    }
 }
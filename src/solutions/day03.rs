@@ -1,44 +1,262 @@
-use regex::Regex;
+use std::fmt::{Display, Formatter};
 use std::fs;
+use std::io::Read;
 
 pub fn solve(puzzle_file_path: &str) -> (String, String) {
-    let input = fs::read_to_string(puzzle_file_path).unwrap();
-    let instructions = parse_instructions(&input);
+    let file = fs::File::open(puzzle_file_path).unwrap();
+    let instructions = read_instructions(file, &mut Scanner::new()).unwrap();
     let solution_one = solve_part_one(&instructions);
     let solution_two = solve_part_two(&instructions);
     (solution_one, solution_two)
 }
+
+// Lists the byte offset of every instruction and near miss in the memory
+pub fn explain(puzzle_file_path: &str) -> Result<String, String> {
+    let file = fs::File::open(puzzle_file_path).map_err(|e| e.to_string())?;
+    let mut lines: Vec<String> = vec![];
+    scan_reader(file, &mut Scanner::new(), |tokens| {
+        lines.extend(tokens.iter().map(|token| token.to_string()))
+    })?;
+    Ok(lines.join("\n"))
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq)]
 enum Instruction {
     MUL(i32, i32),
     DONT,
     DO,
+    // Any instruction registered at runtime
+    OTHER(String, Vec<i32>),
+}
+
+impl Instruction {
+    fn from_token(name: &str, args: &[i32]) -> Instruction {
+        match (name, args) {
+            ("mul", [f1, f2]) => Instruction::MUL(*f1, *f2),
+            ("don't", []) => Instruction::DONT,
+            ("do", []) => Instruction::DO,
+            _ => Instruction::OTHER(name.to_string(), args.to_vec()),
+        }
+    }
+}
+
+const MAX_ARG_DIGITS: usize = 3;
+const CHUNK_SIZE: usize = 4096;
+
+// An instruction is its name followed by `arity` numbers with 1 to 3 digits, separated by
+// commas and enclosed in parentheses, e.g. `mul(2,4)`
+#[derive(Debug, Clone)]
+struct InstructionSpec {
+    name: String,
+    arity: usize,
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Instruction {
+        offset: usize,
+        name: String,
+        args: Vec<i32>,
+    },
+    // Something that started like an instruction including the opening parenthesis, but
+    // broke off. `text` ends with the offending byte, if there is one.
+    NearMiss {
+        offset: usize,
+        text: String,
+    },
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Instruction { offset, name, args } => {
+                let args = args.iter().map(|a| a.to_string()).collect::<Vec<String>>();
+                write!(f, "{:>8}  {}({})", offset, name, args.join(","))
+            }
+            Token::NearMiss { offset, text } => write!(f, "{:>8}  near miss {:?}", offset, text),
+        }
+    }
 }
 
-fn parse_instructions(input: &str) -> Vec<Instruction> {
-    let reg = Regex::new(r"(mul\(\d{1,3},\d{1,3}\)|don't\(\)|do\(\))").unwrap();
-    let instructions = reg
-        .find_iter(input)
-        .map(|needle| needle.as_str())
-        .map(|ins_match| {
-            if ins_match.contains("don") {
-                return Instruction::DONT;
+enum SpecMatch {
+    Complete(usize, Vec<i32>),
+    // Ran out of input before the instruction could be completed
+    Partial,
+    // Didn't match, together with the length of the matched part including the failing byte
+    Failed(usize),
+}
+
+// Tokenizer that is fed the memory in chunks of any size. Only the bytes of an instruction
+// that may still be completed by the next chunk are kept between the calls.
+struct Scanner {
+    specs: Vec<InstructionSpec>,
+    pending: Vec<u8>,
+    offset: usize,
+}
+
+impl Scanner {
+    fn new() -> Scanner {
+        let mut scanner = Scanner {
+            specs: vec![],
+            pending: vec![],
+            offset: 0,
+        };
+        scanner.register("mul", 2);
+        scanner.register("do", 0);
+        scanner.register("don't", 0);
+        scanner
+    }
+
+    fn register(&mut self, name: &str, arity: usize) {
+        self.specs.push(InstructionSpec {
+            name: name.to_string(),
+            arity,
+        });
+    }
+
+    fn feed(&mut self, chunk: &[u8]) -> Vec<Token> {
+        self.pending.extend_from_slice(chunk);
+        self.scan(false)
+    }
+
+    // Flushes everything left at the end of the input
+    fn finish(&mut self) -> Vec<Token> {
+        self.scan(true)
+    }
+
+    fn scan(&mut self, is_last: bool) -> Vec<Token> {
+        let mut tokens: Vec<Token> = vec![];
+        let mut position = 0;
+        'scan: while position < self.pending.len() {
+            let mut longest_match: Option<(usize, &InstructionSpec, Vec<i32>)> = None;
+            let mut near_miss: Option<usize> = None;
+            for spec in &self.specs {
+                match match_spec(spec, &self.pending[position..], is_last) {
+                    SpecMatch::Partial => break 'scan,
+                    SpecMatch::Complete(length, args) => {
+                        if longest_match.as_ref().is_none_or(|(l, _, _)| length > *l) {
+                            longest_match = Some((length, spec, args));
+                        }
+                    }
+                    SpecMatch::Failed(length) => {
+                        if length > spec.name.len() + 1 {
+                            near_miss = near_miss.max(Some(length));
+                        }
+                    }
+                }
             }
-            if ins_match.contains("do") {
-                return Instruction::DO;
+
+            let offset = self.offset + position;
+            if let Some((length, spec, args)) = longest_match {
+                tokens.push(Token::Instruction {
+                    offset,
+                    name: spec.name.clone(),
+                    args,
+                });
+                position += length;
+                continue;
+            }
+            if let Some(length) = near_miss {
+                let end = (position + length).min(self.pending.len());
+                tokens.push(Token::NearMiss {
+                    offset,
+                    text: String::from_utf8_lossy(&self.pending[position..end]).to_string(),
+                });
             }
-            if ins_match.contains("mul") {
-                let factors = ins_match
-                    .replace("mul(", "")
-                    .replace(")", "")
-                    .split(",")
-                    .map(|f| f.parse::<i32>().unwrap())
-                    .collect::<Vec<i32>>();
-                return Instruction::MUL(factors[0], factors[1]);
+            position += 1;
+        }
+        self.pending.drain(..position);
+        self.offset += position;
+        tokens
+    }
+}
+
+fn match_spec(spec: &InstructionSpec, input: &[u8], is_last: bool) -> SpecMatch {
+    let mut position = 0;
+    let mut expect = |expected: &[u8]| -> Result<(), SpecMatch> {
+        for byte in expected {
+            match input.get(position) {
+                None if is_last => return Err(SpecMatch::Failed(position)),
+                None => return Err(SpecMatch::Partial),
+                Some(b) if b != byte => return Err(SpecMatch::Failed(position + 1)),
+                Some(_) => position += 1,
             }
-            panic!("Unknown instruction: {}", ins_match)
-        })
-        .collect::<Vec<Instruction>>();
-    instructions
+        }
+        Ok(())
+    };
+    if let Err(result) = expect(spec.name.as_bytes()).and_then(|_| expect(b"(")) {
+        return result;
+    }
+
+    let mut args: Vec<i32> = Vec::with_capacity(spec.arity);
+    for index in 0..spec.arity {
+        let digits = input[position..]
+            .iter()
+            .take(MAX_ARG_DIGITS)
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        let next = position + digits;
+        if next == input.len() && !is_last {
+            return SpecMatch::Partial;
+        }
+        if digits == 0 {
+            return SpecMatch::Failed((next + 1).min(input.len()));
+        }
+        let number = std::str::from_utf8(&input[position..next]).unwrap();
+        args.push(number.parse().unwrap());
+        position = next;
+
+        let separator: &[u8] = if index + 1 < spec.arity { b"," } else { b")" };
+        match input.get(position) {
+            None if is_last => return SpecMatch::Failed(position),
+            None => return SpecMatch::Partial,
+            Some(b) if b != &separator[0] => return SpecMatch::Failed(position + 1),
+            Some(_) => position += 1,
+        }
+    }
+    if spec.arity == 0 {
+        match input.get(position) {
+            None if is_last => return SpecMatch::Failed(position),
+            None => return SpecMatch::Partial,
+            Some(b')') => position += 1,
+            Some(_) => return SpecMatch::Failed(position + 1),
+        }
+    }
+    SpecMatch::Complete(position, args)
+}
+
+fn tokens_to_instructions(tokens: Vec<Token>) -> impl Iterator<Item = Instruction> {
+    tokens.into_iter().filter_map(|token| match token {
+        Token::Instruction { name, args, .. } => Some(Instruction::from_token(&name, &args)),
+        Token::NearMiss { .. } => None,
+    })
+}
+
+// Feeds the input chunk by chunk into the scanner and hands over the tokens of every chunk
+fn scan_reader(
+    mut reader: impl Read,
+    scanner: &mut Scanner,
+    mut consume: impl FnMut(Vec<Token>),
+) -> Result<(), String> {
+    let mut buffer = [0u8; CHUNK_SIZE];
+    loop {
+        let count = reader.read(&mut buffer).map_err(|e| e.to_string())?;
+        if count == 0 {
+            break;
+        }
+        consume(scanner.feed(&buffer[..count]));
+    }
+    consume(scanner.finish());
+    Ok(())
+}
+
+fn read_instructions(reader: impl Read, scanner: &mut Scanner) -> Result<Vec<Instruction>, String> {
+    let mut instructions: Vec<Instruction> = vec![];
+    scan_reader(reader, scanner, |tokens| {
+        instructions.extend(tokens_to_instructions(tokens))
+    })?;
+    Ok(instructions)
 }
 
 fn solve_part_one(instructions: &Vec<Instruction>) -> String {
@@ -64,6 +282,7 @@ fn solve_part_two(instructions: &Vec<Instruction>) -> String {
                     line_sum += f1 * f2
                 }
             }
+            Instruction::OTHER(_, _) => {}
         }
     }
     line_sum.to_string()
@@ -73,6 +292,10 @@ fn solve_part_two(instructions: &Vec<Instruction>) -> String {
 mod tests {
     use super::*;
 
+    fn parse_instructions(input: &str) -> Vec<Instruction> {
+        read_instructions(input.as_bytes(), &mut Scanner::new()).unwrap()
+    }
+
     #[test]
     fn test_part_one() {
         let input = fs::read_to_string("./resources/day03/example.txt").unwrap();
@@ -88,4 +311,81 @@ mod tests {
         let solution_two = solve_part_two(&puzzle);
         assert_eq!(solution_two, "48");
     }
+
+    const EXAMPLE: &str =
+        "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+
+    #[test]
+    fn test_streaming_scanner() {
+        let instructions = parse_instructions(EXAMPLE);
+        assert_eq!(solve_part_one(&instructions), "161");
+        assert_eq!(solve_part_two(&instructions), "48");
+
+        let mut scanner = Scanner::new();
+        let mut tokens = scanner.feed(EXAMPLE.as_bytes());
+        tokens.extend(scanner.finish());
+        let near_misses = tokens
+            .iter()
+            .filter_map(|token| match token {
+                Token::NearMiss { offset, text } => Some((*offset, text.as_str())),
+                _ => None,
+            })
+            .collect::<Vec<(usize, &str)>>();
+        assert_eq!(near_misses, vec![(37, "mul(32,64]")]);
+        assert_eq!(
+            tokens[0],
+            Token::Instruction {
+                offset: 1,
+                name: "mul".to_string(),
+                args: vec![2, 4]
+            }
+        );
+
+        // Feeding one byte at a time yields the same tokens
+        let mut scanner = Scanner::new();
+        let mut byte_tokens: Vec<Token> = vec![];
+        for byte in EXAMPLE.as_bytes() {
+            byte_tokens.extend(scanner.feed(&[*byte]));
+        }
+        byte_tokens.extend(scanner.finish());
+        assert_eq!(byte_tokens, tokens);
+    }
+
+    #[test]
+    fn test_registered_instructions() {
+        let mut scanner = Scanner::new();
+        scanner.register("add", 3);
+        scanner.register("mult", 1);
+        let input = "add(1,2,3)mul(4*mult(7)mul(1000,1)do(x)add(1,2)mul(2,";
+        let instructions = read_instructions(input.as_bytes(), &mut scanner).unwrap();
+        assert_eq!(
+            instructions,
+            vec![
+                Instruction::OTHER("add".to_string(), vec![1, 2, 3]),
+                Instruction::OTHER("mult".to_string(), vec![7]),
+            ]
+        );
+
+        let mut scanner = Scanner::new();
+        scanner.register("add", 3);
+        let mut tokens = scanner.feed(input.as_bytes());
+        tokens.extend(scanner.finish());
+        let near_misses = tokens
+            .into_iter()
+            .filter_map(|token| match token {
+                Token::NearMiss { offset, text } => Some((offset, text)),
+                _ => None,
+            })
+            .collect::<Vec<(usize, String)>>();
+        assert_eq!(
+            near_misses,
+            vec![
+                (10, "mul(4*".to_string()),
+                (23, "mul(1000".to_string()),
+                (34, "do(x".to_string()),
+                (39, "add(1,2)".to_string()),
+                (47, "mul(2,".to_string()),
+            ]
+        );
+    }
 }
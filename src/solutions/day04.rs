@@ -1,105 +1,251 @@
+use std::collections::HashSet;
+
 pub fn solve(puzzle_file_path: &str) -> (String, String) {
-    let (width, height, matches_char_at_pos) = parse_puzzle(puzzle_file_path);
-    let mut sum_part_one = 0;
-    let mut sum_part_two = 0;
-
-    for y in 0..height {
-        for x in 0..width {
-            sum_part_one += check_position_for_word("XMAS", &matches_char_at_pos, (x, y));
-            sum_part_two += check_position_for_mas_cross(&matches_char_at_pos, (x, y))
-        }
-    }
+    let input = std::fs::read_to_string(puzzle_file_path).unwrap();
+    let grid = parse_puzzle(&input);
+    let sum_part_one = search_words(&grid, &["XMAS"]).len();
+    let sum_part_two = search(&grid, &Stencil::parse(MAS_CROSS)).len();
 
     (sum_part_one.to_string(), sum_part_two.to_string())
 }
 
-fn check_position_for_word(
-    word: &str,
-    check_pos_for_char: impl Fn(i32, i32, char) -> bool,
-    pos: (i32, i32),
-) -> i32 {
-    if !check_pos_for_char(pos.0, pos.1, word.chars().nth(0).unwrap()) {
-        return 0;
-    }
+// Prints the grid with the matches of both parts highlighted
+pub fn explain(puzzle_file_path: &str) -> Result<String, String> {
+    let input = std::fs::read_to_string(puzzle_file_path).map_err(|e| e.to_string())?;
+    let grid = parse_puzzle(&input);
+    let words = search_words(&grid, &["XMAS"])
+        .into_iter()
+        .map(|(_, m)| m)
+        .collect::<Vec<Match>>();
+    let crosses = search(&grid, &Stencil::parse(MAS_CROSS));
+    Ok(format!(
+        "{} times XMAS:\n{}\n{} times X-MAS:\n{}",
+        words.len(),
+        grid.highlight(&words, true),
+        crosses.len(),
+        grid.highlight(&crosses, true)
+    ))
+}
 
-    let search_directions: Vec<fn((i32, i32)) -> (i32, i32)> = Vec::from([
-        |(x, y)| (x + 1, y),     // Right
-        |(x, y)| (x, y - 1),     // Down
-        |(x, y)| (x + 1, y + 1), // Top-Right
-        |(x, y)| (x + 1, y - 1), // Down-Right
-        |(x, y)| (x - 1, y),     // Left
-        |(x, y)| (x, y + 1),     // Top
-        |(x, y)| (x - 1, y - 1), // Bot-Left
-        |(x, y)| (x - 1, y + 1), // Top-Left
-    ]);
-
-    search_directions
-        .iter()
-        .filter(|direction| check_in_direction(word, pos, direction, &check_pos_for_char))
-        .count() as i32
+// Two MAS crossing at the A, '.' matches any character
+const MAS_CROSS: &str = "M.S\n.A.\nM.S";
+
+const ANSI_HIGHLIGHT: &str = "\x1b[38;2;76;175;80m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+type Position = (i32, i32);
+
+const DIRECTIONS: [Position; 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+
+struct Grid {
+    width: i32,
+    height: i32,
+    cells: Vec<char>,
 }
 
-fn check_position_for_mas_cross(
-    check_pos: impl Fn(i32, i32, char) -> bool,
-    (x, y): (i32, i32),
-) -> i32 {
-    if !check_pos(x, y, 'A') {
-        0
-    } else {
-        let diag_one = check_pos(x - 1, y + 1, 'M') && check_pos(x + 1, y - 1, 'S')
-            || check_pos(x - 1, y + 1, 'S') && check_pos(x + 1, y - 1, 'M');
-        let diag_two = check_pos(x - 1, y - 1, 'M') && check_pos(x + 1, y + 1, 'S')
-            || check_pos(x - 1, y - 1, 'S') && check_pos(x + 1, y + 1, 'M');
-        if diag_one && diag_two {
-            1
+impl Grid {
+    fn at(&self, (x, y): Position) -> Option<char> {
+        if x < 0 || x >= self.width || y < 0 || y >= self.height {
+            None
         } else {
-            0
+            Some(self.cells[(y * self.width + x) as usize])
+        }
+    }
+
+    // Prints the grid like the puzzle description, with every character that isn't part of a
+    // match replaced by '.'. Colored output keeps all characters and highlights the matches.
+    fn highlight(&self, matches: &[Match], colored: bool) -> String {
+        let highlighted: HashSet<Position> = matches
+            .iter()
+            .flat_map(|m| m.cells.iter().copied())
+            .collect();
+        let mut lines: Vec<String> = vec![];
+        for y in 0..self.height {
+            let mut line = String::new();
+            for x in 0..self.width {
+                let c = self.at((x, y)).unwrap();
+                match (highlighted.contains(&(x, y)), colored) {
+                    (true, true) => line.push_str(&format!("{ANSI_HIGHLIGHT}{c}{ANSI_RESET}")),
+                    (false, false) => line.push('.'),
+                    _ => line.push(c),
+                }
+            }
+            lines.push(line);
         }
+        lines.join("\n")
     }
 }
 
-fn check_in_direction(
-    word: &str,
-    start_position: (i32, i32),
-    position_update: impl Fn((i32, i32)) -> (i32, i32),
-    check_pos: impl Fn(i32, i32, char) -> bool,
-) -> bool {
-    if word.is_empty() {
-        return true;
+// Rotation in quarter turns clockwise, applied after the optional mirroring along the y-axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Orientation {
+    quarter_turns: u8,
+    mirrored: bool,
+}
+
+impl Orientation {
+    fn all() -> Vec<Orientation> {
+        [false, true]
+            .iter()
+            .flat_map(|&mirrored| {
+                (0..4).map(move |quarter_turns| Orientation {
+                    quarter_turns,
+                    mirrored,
+                })
+            })
+            .collect()
     }
-    let mut chars = word.chars();
-    let head = chars.next().unwrap();
-    let tail = chars.as_str();
-
-    if check_pos(start_position.0, start_position.1, head) == true {
-        check_in_direction(
-            tail,
-            position_update(start_position),
-            position_update,
-            check_pos,
-        )
-    } else {
-        false
+
+    fn apply(&self, (x, y): Position) -> Position {
+        let (mut x, mut y) = if self.mirrored { (-x, y) } else { (x, y) };
+        for _ in 0..self.quarter_turns {
+            (x, y) = (-y, x);
+        }
+        (x, y)
     }
 }
 
-fn parse_puzzle(file_path: &str) -> (i32, i32, Box<dyn Fn(i32, i32, char) -> bool>) {
-    let mut input = std::fs::read_to_string(file_path).unwrap();
-    let width = (input.find('\n').unwrap() - 1) as i32;
-    let height = input.len() as i32 / (width + 1);
+// Characters relative to the top left corner of the pattern
+#[derive(Debug, Clone)]
+struct Stencil {
+    cells: Vec<(Position, char)>,
+}
 
-    input.retain(|c| !c.is_whitespace());
-    let flat_input: Vec<char> = input.chars().collect();
+impl Stencil {
+    fn parse(pattern: &str) -> Stencil {
+        let mut cells: Vec<(Position, char)> = vec![];
+        for (y, line) in pattern.lines().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                if c != '.' {
+                    cells.push(((x as i32, y as i32), c));
+                }
+            }
+        }
+        Stencil { cells }
+    }
 
-    let matches_char_at_pos = Box::new(move |x: i32, y: i32, c: char| {
-        if x < 0 || x >= width || y < 0 || y >= height {
-            false
-        } else {
-            flat_input[(y * width + x) as usize] == c
+    // A word written in every straight and diagonal direction, every variant starts with the
+    // first character at the origin
+    fn word_variants(word: &str) -> Vec<(Position, Stencil)> {
+        let mut shapes: HashSet<Vec<(Position, char)>> = HashSet::new();
+        DIRECTIONS
+            .iter()
+            .map(|&(dx, dy)| {
+                let cells = word
+                    .chars()
+                    .enumerate()
+                    .map(|(i, c)| ((i as i32 * dx, i as i32 * dy), c))
+                    .collect();
+                ((dx, dy), Stencil { cells })
+            })
+            .filter(|(_, stencil)| shapes.insert(stencil.normalized()))
+            .collect()
+    }
+
+    fn transform(&self, orientation: &Orientation) -> Stencil {
+        Stencil {
+            cells: self
+                .cells
+                .iter()
+                .map(|(position, c)| (orientation.apply(*position), *c))
+                .collect(),
         }
-    });
+    }
+
+    // The stencil moved to the origin, so that stencils of the same shape are equal
+    fn normalized(&self) -> Vec<(Position, char)> {
+        let min_x = self.cells.iter().map(|((x, _), _)| *x).min().unwrap_or(0);
+        let min_y = self.cells.iter().map(|((_, y), _)| *y).min().unwrap_or(0);
+        let mut cells = self
+            .cells
+            .iter()
+            .map(|((x, y), c)| ((x - min_x, y - min_y), *c))
+            .collect::<Vec<(Position, char)>>();
+        cells.sort();
+        cells
+    }
 
-    (width, height, matches_char_at_pos)
+    // All rotations and reflections that result in a different shape. Symmetric patterns would
+    // otherwise match the same cells multiple times.
+    fn variants(&self) -> Vec<(Position, Stencil)> {
+        let mut shapes: HashSet<Vec<(Position, char)>> = HashSet::new();
+        Orientation::all()
+            .into_iter()
+            .map(|orientation| (orientation.apply((1, 0)), self.transform(&orientation)))
+            .filter(|(_, stencil)| shapes.insert(stencil.normalized()))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Match {
+    // Position of the top left corner of the untransformed stencil, the first character of words
+    anchor: Position,
+    // Direction in which a word is read or in which the top edge of a stencil points
+    direction: Position,
+    cells: Vec<Position>,
+}
+
+fn search(grid: &Grid, stencil: &Stencil) -> Vec<Match> {
+    search_variants(grid, &stencil.variants())
+}
+
+fn search_words<'a>(grid: &Grid, words: &[&'a str]) -> Vec<(&'a str, Match)> {
+    words
+        .iter()
+        .flat_map(|word| {
+            search_variants(grid, &Stencil::word_variants(word))
+                .into_iter()
+                .map(move |m| (*word, m))
+        })
+        .collect()
+}
+
+fn search_variants(grid: &Grid, variants: &[(Position, Stencil)]) -> Vec<Match> {
+    let mut matches: Vec<Match> = vec![];
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            for (direction, variant) in variants {
+                let is_match = variant
+                    .cells
+                    .iter()
+                    .all(|((dx, dy), c)| grid.at((x + dx, y + dy)) == Some(*c));
+                if is_match {
+                    matches.push(Match {
+                        anchor: (x, y),
+                        direction: *direction,
+                        cells: variant
+                            .cells
+                            .iter()
+                            .map(|((dx, dy), _)| (x + dx, y + dy))
+                            .collect(),
+                    });
+                }
+            }
+        }
+    }
+    matches
+}
+
+fn parse_puzzle(input: &str) -> Grid {
+    let lines = input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<&str>>();
+    Grid {
+        width: lines.first().map_or(0, |line| line.trim().chars().count()) as i32,
+        height: lines.len() as i32,
+        cells: lines.iter().flat_map(|line| line.trim().chars()).collect(),
+    }
 }
 
 #[cfg(test)]
@@ -112,4 +258,54 @@ mod tests {
         assert_eq!(solution_one, "18");
         assert_eq!(solution_two, "9");
     }
+
+    const EXAMPLE: &str = "MMMSXXMASM\nMSAMXMSMSA\nAMXSXMAAMM\nMSAMASMSMX\nXMASAMXAMM\nXXAMMXXAMA\nSMSMSASXSS\nSAXAMASAAA\nMAMMMXMMMM\nMXMXAXMASX";
+
+    #[test]
+    fn test_word_search() {
+        let grid = parse_puzzle(EXAMPLE);
+        let matches = search_words(&grid, &["XMAS"]);
+        assert_eq!(matches.len(), 18);
+        assert_eq!(search(&grid, &Stencil::parse(MAS_CROSS)).len(), 9);
+
+        // The first match is diagonal, starting at the first X in the first line
+        let (_, first) = &matches[0];
+        assert_eq!(first.anchor, (4, 0));
+        assert_eq!(first.direction, (1, 1));
+        assert_eq!(first.cells, vec![(4, 0), (5, 1), (6, 2), (7, 3)]);
+        let directions: HashSet<Position> = matches.iter().map(|(_, m)| m.direction).collect();
+        assert_eq!(directions.len(), 8);
+
+        let small = parse_puzzle("..X...\n.SAMX.\n.A..A.\nXMAS.S\n.X....");
+        let matches = search_words(&small, &["XMAS", "AX"])
+            .into_iter()
+            .map(|(_, m)| m)
+            .collect::<Vec<Match>>();
+        assert_eq!(matches.len(), 8);
+        assert_eq!(
+            small.highlight(&matches[..4], false),
+            "..X...\n.SAMX.\n.A..A.\nXMAS.S\n.X...."
+        );
+        assert_eq!(
+            small.highlight(&[], false),
+            "......\n......\n......\n......\n......"
+        );
+        assert!(small
+            .highlight(&matches, true)
+            .contains("\x1b[38;2;76;175;80mX\x1b[0m"));
+    }
+
+    #[test]
+    fn test_stencil_variants() {
+        assert_eq!(Stencil::parse(MAS_CROSS).variants().len(), 4);
+        assert_eq!(Stencil::word_variants("XMAS").len(), 8);
+        // Palindromes read the same in both directions
+        assert_eq!(Stencil::word_variants("ABA").len(), 4);
+        assert_eq!(Stencil::parse("AB\nBA").variants().len(), 2);
+        // An L shape is different in every orientation
+        assert_eq!(Stencil::parse("A.\nA.\nAA").variants().len(), 8);
+
+        let grid = parse_puzzle("AAA\nA.A\nAAA");
+        assert_eq!(search(&grid, &Stencil::parse("AA\nA.")).len(), 4);
+    }
 }
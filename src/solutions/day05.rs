@@ -8,75 +8,173 @@ pub fn solve(puzzle_input_path: &str) -> (String, String) {
     (solution_one.to_string(), solution_two.to_string())
 }
 
+// Explains which rules every incorrect update violates and how it is ordered correctly
+pub fn explain(puzzle_input_path: &str) -> Result<String, String> {
+    let input = std::fs::read_to_string(puzzle_input_path).map_err(|e| e.to_string())?;
+    let (constraints, updates) = parse_puzzle(&input);
+    let mut lines: Vec<String> = vec![];
+    for page_update in &updates {
+        if is_page_order_correct(page_update, &constraints) {
+            continue;
+        }
+        lines.push(explain_page_order(page_update, &constraints));
+        match sort_pages(page_update, &constraints) {
+            Ok(sorted) => lines.push(format!("  ordered {:?}", sorted)),
+            Err(cycle) => lines.push(format!("  {}", format_cycle(page_update, &cycle))),
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
 fn solve_part_one(constraints: &HashMap<u8, HashSet<u8>>, updates: &Vec<Vec<u8>>) -> String {
     let solution: i32 = updates
         .iter()
-        .filter(|page_update| is_page_order_correct(page_update, &constraints))
+        .filter(|page_update| is_page_order_correct(page_update, constraints))
         .map(|page_update| page_update[page_update.len() / 2] as i32)
         .sum();
     solution.to_string()
 }
 
 fn solve_part_two(constraints: &HashMap<u8, HashSet<u8>>, updates: &Vec<Vec<u8>>) -> String {
-    let solution: i32 = updates
-        .iter()
-        .filter(|page_update| !is_page_order_correct(page_update, &constraints))
-        .map(|page| fix_page_order(page, &constraints))
-        .map(|page_update| page_update[page_update.len() / 2] as i32)
-        .sum();
-
+    let mut solution: i32 = 0;
+    for page_update in updates {
+        if is_page_order_correct(page_update, constraints) {
+            continue;
+        }
+        match sort_pages(page_update, constraints) {
+            Ok(page_update) => solution += page_update[page_update.len() / 2] as i32,
+            Err(cycle) => return format_cycle(page_update, &cycle),
+        }
+    }
     solution.to_string()
 }
 
-fn is_page_order_correct(page_order: &Vec<u8>, constraints: &HashMap<u8, HashSet<u8>>) -> bool {
-    // Validates page order by recursively checking if the last element violates any constraint
-    // For an easier implementation the page order gets reversed
-    let mut page_order_rev = page_order.clone();
-    page_order_rev.reverse();
-    for i in 0..page_order_rev.len() {
-        match constraints.get(&page_order_rev[i]) {
-            Some(page_constraints) => {
-                for j in i + 1..page_order_rev.len() {
-                    if page_constraints.contains(&page_order_rev[j]) {
-                        return false;
-                    }
+// A rule (before, after) demands that page `before` is printed before page `after`
+type Rule = (u8, u8);
+
+fn is_page_order_correct(page_order: &[u8], constraints: &HashMap<u8, HashSet<u8>>) -> bool {
+    find_rule_violations(page_order, constraints).is_empty()
+}
+
+// Lists every rule the update violates, ordered by the position of the page that should
+// have been printed first
+fn find_rule_violations(page_order: &[u8], constraints: &HashMap<u8, HashSet<u8>>) -> Vec<Rule> {
+    let positions: HashMap<u8, usize> = page_order
+        .iter()
+        .enumerate()
+        .map(|(index, page)| (*page, index))
+        .collect();
+
+    let mut violations: Vec<(usize, Rule)> = vec![];
+    for (index, page) in page_order.iter().enumerate() {
+        if let Some(pages_after) = constraints.get(page) {
+            for page_after in pages_after {
+                if positions.get(page_after).is_some_and(|&i| i < index) {
+                    violations.push((index, (*page, *page_after)));
                 }
             }
-            None => {}
         }
     }
-    true
+    violations.sort();
+    violations.into_iter().map(|(_, rule)| rule).collect()
 }
 
-fn fix_page_order(page_order: &Vec<u8>, constraints: &HashMap<u8, HashSet<u8>>) -> Vec<u8> {
-    let mut page_order_rev = page_order.clone();
-    page_order_rev.reverse();
-    for i in 0..page_order_rev.len() {
-        loop {
-            let mut had_swap = false;
-            // Check if any constraint is violated and fix it
-            match constraints.get(&page_order_rev[i]) {
-                Some(page_constraints) => {
-                    for j in i + 1..page_order_rev.len() {
-                        if page_constraints.contains(&page_order_rev[j]) {
-                            page_order_rev.swap(i, j);
-                            // Curren page has changed, break to re-run with new constraints
-                            had_swap = true;
-                            break;
-                        }
-                    }
-                }
-                None => {}
-            }
-            // All constraints are satisfied, this page is in the right place.
-            // -> Go to next page
-            if !had_swap {
-                break;
-            }
+fn explain_page_order(page_order: &[u8], constraints: &HashMap<u8, HashSet<u8>>) -> String {
+    let violations = find_rule_violations(page_order, constraints);
+    if violations.is_empty() {
+        return format!("{:?} is in the right order", page_order);
+    }
+    let rules = violations
+        .iter()
+        .map(|(before, after)| format!("{}|{}", before, after))
+        .collect::<Vec<String>>();
+    format!("{:?} violates {}", page_order, rules.join(", "))
+}
+
+fn format_cycle(page_order: &[u8], cycle: &[u8]) -> String {
+    let cycle = cycle
+        .iter()
+        .chain(cycle.first())
+        .map(|page| page.to_string())
+        .collect::<Vec<String>>();
+    format!(
+        "Contradicting rules for {:?}: {}",
+        page_order,
+        cycle.join(" -> ")
+    )
+}
+
+// Topological sort (Kahn's algorithm) of the pages in the update, only considering the rules
+// between them. Pages without an order between them keep their relative position. If the rules
+// contradict each other, the pages of one cycle are returned as error.
+fn sort_pages(
+    page_order: &[u8],
+    constraints: &HashMap<u8, HashSet<u8>>,
+) -> Result<Vec<u8>, Vec<u8>> {
+    let pages: HashSet<u8> = page_order.iter().copied().collect();
+    let successors = |page: &u8| -> Vec<u8> {
+        constraints
+            .get(page)
+            .map(|after| {
+                after
+                    .iter()
+                    .filter(|p| pages.contains(p))
+                    .copied()
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let mut in_degree: HashMap<u8, usize> = page_order.iter().map(|page| (*page, 0)).collect();
+    for page in page_order {
+        for successor in successors(page) {
+            *in_degree.get_mut(&successor).unwrap() += 1;
+        }
+    }
+
+    let mut sorted: Vec<u8> = Vec::with_capacity(page_order.len());
+    let mut done: HashSet<u8> = HashSet::new();
+    while sorted.len() < page_order.len() {
+        let Some(&next) = page_order
+            .iter()
+            .find(|page| !done.contains(page) && in_degree[page] == 0)
+        else {
+            let remaining: Vec<u8> = page_order
+                .iter()
+                .filter(|page| !done.contains(page))
+                .copied()
+                .collect();
+            return Err(find_cycle(&remaining, &successors));
+        };
+        done.insert(next);
+        sorted.push(next);
+        for successor in successors(&next) {
+            *in_degree.get_mut(&successor).unwrap() -= 1;
+        }
+    }
+    Ok(sorted)
+}
+
+// Every remaining page has a predecessor among the remaining pages, so walking backwards
+// eventually visits a page twice
+fn find_cycle(remaining: &[u8], successors: &dyn Fn(&u8) -> Vec<u8>) -> Vec<u8> {
+    let predecessor = |page: u8| -> u8 {
+        *remaining
+            .iter()
+            .find(|p| successors(p).contains(&page))
+            .unwrap()
+    };
+
+    let mut path: Vec<u8> = vec![remaining[0]];
+    loop {
+        let previous = predecessor(*path.last().unwrap());
+        if let Some(start) = path.iter().position(|&p| p == previous) {
+            let mut cycle = path[start..].to_vec();
+            cycle.reverse();
+            return cycle;
         }
+        path.push(previous);
     }
-    page_order_rev.reverse();
-    page_order_rev
 }
 
 fn parse_puzzle(input: &str) -> (HashMap<u8, HashSet<u8>>, Vec<Vec<u8>>) {
@@ -134,4 +232,38 @@ mod tests {
         let s = solve_part_two(&c, &u);
         assert_eq!(s, "123")
     }
+
+    const RULES: &str = "47|53\n97|13\n97|61\n97|47\n75|29\n61|13\n75|53\n29|13\n97|29\n53|29\n61|53\n97|53\n61|29\n47|13\n75|47\n97|75\n47|61\n75|61\n47|29\n75|13\n53|13\n\n";
+
+    #[test]
+    fn test_topological_sort() {
+        let input = format!(
+            "{}75,47,61,53,29\n75,97,47,61,53\n61,13,29\n97,13,75,29,47\n",
+            RULES
+        );
+        let (c, u) = parse_puzzle(&input);
+        assert_eq!(solve_part_one(&c, &u), "61");
+        assert_eq!(solve_part_two(&c, &u), "123");
+
+        assert_eq!(sort_pages(&u[1], &c), Ok(vec![97, 75, 47, 61, 53]));
+        assert_eq!(sort_pages(&u[2], &c), Ok(vec![61, 29, 13]));
+        assert_eq!(sort_pages(&u[3], &c), Ok(vec![97, 75, 47, 29, 13]));
+
+        assert_eq!(find_rule_violations(&u[0], &c), vec![]);
+        assert_eq!(find_rule_violations(&u[1], &c), vec![(97, 75)]);
+        assert_eq!(find_rule_violations(&u[2], &c), vec![(29, 13)]);
+        assert_eq!(explain_page_order(&u[2], &c), "[61, 13, 29] violates 29|13");
+    }
+
+    #[test]
+    fn test_contradicting_rules() {
+        let (c, u) = parse_puzzle("1|2\n2|3\n3|1\n3|4\n\n4,1,2,3\n");
+        let cycle = sort_pages(&u[0], &c).unwrap_err();
+        assert_eq!(cycle.len(), 3);
+        for (i, page) in cycle.iter().enumerate() {
+            let next = cycle[(i + 1) % cycle.len()];
+            assert!(c[page].contains(&next));
+        }
+        assert!(solve_part_two(&c, &u).starts_with("Contradicting rules for [4, 1, 2, 3]"));
+    }
 }
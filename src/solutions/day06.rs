@@ -9,6 +9,31 @@ enum Direction {
     South,
     West,
 }
+
+impl Direction {
+    fn turn_right(&self) -> Direction {
+        match self {
+            Direction::North => Direction::East,
+            Direction::East => Direction::South,
+            Direction::South => Direction::West,
+            Direction::West => Direction::North,
+        }
+    }
+
+    fn delta(&self) -> (i32, i32) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::East => (1, 0),
+            Direction::South => (0, 1),
+            Direction::West => (-1, 0),
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
 pub fn solve(puzzle_input_path: &str) -> (String, String) {
     let input = std::fs::read_to_string(puzzle_input_path).unwrap();
     let (width, height, position, obstacles) = parse_puzzle(&input);
@@ -21,6 +46,32 @@ pub fn solve(puzzle_input_path: &str) -> (String, String) {
     (solution_one, solution_two)
 }
 
+// Lists every obstacle that traps the guard with the steps of one lap and the cells the
+// guard turns in
+pub fn explain(puzzle_input_path: &str) -> Result<String, String> {
+    let input = std::fs::read_to_string(puzzle_input_path).map_err(|e| e.to_string())?;
+    let (width, height, position, obstacles) = parse_puzzle(&input);
+    let lab = JumpTable::new(width, height, &obstacles);
+    let mut lines: Vec<String> = vec![];
+    for obstacle in find_looping_obstacles(&lab, &position, &obstacles) {
+        let guard_loop = lab
+            .find_loop(&position, &obstacle)
+            .ok_or(format!("Obstacle {:?} doesn't trap the guard", obstacle))?;
+        let turns = guard_loop
+            .turns
+            .iter()
+            .map(|(turn, _)| format!("{:?}", turn))
+            .collect::<Vec<String>>();
+        lines.push(format!(
+            "{:?}: loop of {} steps turning at {}",
+            obstacle,
+            guard_loop.positions.len(),
+            turns.join(" ")
+        ));
+    }
+    Ok(lines.join("\n"))
+}
+
 fn solve_part_one(
     width: i32,
     height: i32,
@@ -94,32 +145,216 @@ fn solve_part_two(
     guard_position: &Position,
     obstacles: &Obstacles,
 ) -> Result<i32, String> {
-    let mut obstacles_extended = obstacles.clone();
-    let mut possible_looping_obstacles = 0;
+    let lab = JumpTable::new(width, height, obstacles);
+    Ok(find_looping_obstacles(&lab, guard_position, obstacles).len() as i32)
+}
 
-    let default_path = simulate_guard(width, height, &guard_position, &obstacles)?;
+fn find_looping_obstacles(
+    lab: &JumpTable,
+    guard_position: &Position,
+    obstacles: &Obstacles,
+) -> Vec<Position> {
+    let mut seen_states: Vec<u32> = vec![0; lab.cell_count() * 4];
+    let mut visited: Vec<bool> = vec![false; lab.cell_count()];
+    let mut looping_obstacles: Vec<Position> = vec![];
 
-    for test_obstacle in default_path.keys() {
-        if &guard_position == &test_obstacle {
+    // Walk the original path. An obstacle placed in a cell the guard is about to enter for the
+    // first time only changes the path from here on, so each trial starts right in front of it.
+    let mut position = *guard_position;
+    let mut direction = Direction::North;
+    visited[lab.index(&position)] = true;
+    let mut trial = 0;
+    loop {
+        let (dx, dy) = direction.delta();
+        let next_position = (position.0 + dx, position.1 + dy);
+        if !lab.contains(&next_position) {
+            break;
+        }
+        if obstacles.contains(&next_position) {
+            direction = direction.turn_right();
             continue;
         }
-        obstacles_extended.insert(test_obstacle.clone());
+        if !visited[lab.index(&next_position)] {
+            trial += 1;
+            if lab
+                .find_loop_states(position, direction, next_position, &mut seen_states, trial)
+                .is_some()
+            {
+                looping_obstacles.push(next_position);
+            }
+            visited[lab.index(&next_position)] = true;
+        }
+        position = next_position;
+    }
+    looping_obstacles
+}
+
+// Describes the loop the guard ends up in. `turns` holds every cell the guard turns in
+// together with the new direction, `positions` one cell per step of a lap in walking order.
+// A cell the guard turns around in is only listed once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GuardLoop {
+    positions: Vec<Position>,
+    turns: Vec<GuardState>,
+}
 
-        match simulate_guard(width, height, &guard_position, &obstacles_extended) {
-            Ok(_) => {}
-            Err(_) => {
-                possible_looping_obstacles += 1;
+type GuardState = (Position, Direction);
+
+// For every free cell and direction, the last cell the guard reaches before hitting an
+// obstacle, or `None` if the guard leaves the map.
+struct JumpTable {
+    width: i32,
+    height: i32,
+    stops: [Vec<Option<Position>>; 4],
+}
+
+impl JumpTable {
+    fn new(width: i32, height: i32, obstacles: &Obstacles) -> JumpTable {
+        let cell_count = (width * height) as usize;
+        let mut stops: [Vec<Option<Position>>; 4] = std::array::from_fn(|_| vec![None; cell_count]);
+        let mut table = JumpTable {
+            width,
+            height,
+            stops: Default::default(),
+        };
+
+        for direction in [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ] {
+            let (dx, dy) = direction.delta();
+            // Sweep against the walking direction, so the next obstacle is always known
+            for y in 0..height {
+                for x in 0..width {
+                    let (x, y) = (
+                        if dx > 0 { width - 1 - x } else { x },
+                        if dy > 0 { height - 1 - y } else { y },
+                    );
+                    let ahead = (x + dx, y + dy);
+                    let stop = if !table.contains(&ahead) {
+                        None
+                    } else if obstacles.contains(&ahead) {
+                        Some((x, y))
+                    } else {
+                        stops[direction.index()][table.index(&ahead)]
+                    };
+                    stops[direction.index()][table.index(&(x, y))] = stop;
+                }
             }
         }
-        obstacles_extended.remove(&test_obstacle);
+        table.stops = stops;
+        table
     }
 
-    Ok(possible_looping_obstacles)
+    fn cell_count(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+
+    fn contains(&self, position: &Position) -> bool {
+        position.0 >= 0 && position.0 < self.width && position.1 >= 0 && position.1 < self.height
+    }
+
+    fn index(&self, position: &Position) -> usize {
+        (position.1 * self.width + position.0) as usize
+    }
+
+    // Like the jump table lookup, but stops in front of `extra_obstacle` if it's in the way
+    fn next_stop(
+        &self,
+        position: Position,
+        direction: Direction,
+        extra_obstacle: Position,
+    ) -> Option<Position> {
+        let stop = self.stops[direction.index()][self.index(&position)];
+        let (dx, dy) = direction.delta();
+        // Distance along the walking direction, negative if behind the guard
+        let distance = |p: &Position| (p.0 - position.0) * dx + (p.1 - position.1) * dy;
+        let in_line = if dx == 0 {
+            extra_obstacle.0 == position.0
+        } else {
+            extra_obstacle.1 == position.1
+        };
+        let extra_distance = distance(&extra_obstacle);
+        if in_line && extra_distance > 0 && stop.is_none_or(|s| extra_distance <= distance(&s)) {
+            return Some((extra_obstacle.0 - dx, extra_obstacle.1 - dy));
+        }
+        stop
+    }
+
+    // Follows the guard from one stop to the next. Returns all stops in order and the index of
+    // the first stop that's part of the loop, or `None` if the guard leaves the map.
+    fn find_loop_states(
+        &self,
+        mut position: Position,
+        mut direction: Direction,
+        extra_obstacle: Position,
+        seen_states: &mut [u32],
+        trial: u32,
+    ) -> Option<(Vec<GuardState>, usize)> {
+        let mut states: Vec<GuardState> = vec![];
+        loop {
+            let stop = self.next_stop(position, direction, extra_obstacle)?;
+            let state_index = self.index(&stop) * 4 + direction.index();
+            if seen_states[state_index] == trial {
+                let loop_start = states
+                    .iter()
+                    .position(|&state| state == (stop, direction))
+                    .unwrap();
+                return Some((states, loop_start));
+            }
+            seen_states[state_index] = trial;
+            states.push((stop, direction));
+            position = stop;
+            direction = direction.turn_right();
+        }
+    }
+
+    fn find_loop(&self, guard_position: &Position, extra_obstacle: &Position) -> Option<GuardLoop> {
+        let mut seen_states: Vec<u32> = vec![0; self.cell_count() * 4];
+        let (states, loop_start) = self.find_loop_states(
+            *guard_position,
+            Direction::North,
+            *extra_obstacle,
+            &mut seen_states,
+            1,
+        )?;
+
+        let turns = states[loop_start..]
+            .iter()
+            .map(|(position, direction)| (*position, direction.turn_right()))
+            .collect::<Vec<GuardState>>();
+
+        let mut positions: Vec<Position> = vec![];
+        for (i, (position, direction)) in turns.iter().enumerate() {
+            let (next_turn, _) = turns[(i + 1) % turns.len()];
+            let (dx, dy) = direction.delta();
+            let mut current = *position;
+            // Turning twice in the same cell doesn't take a step
+            if positions.last() != Some(&current) {
+                positions.push(current);
+            }
+            while current != next_turn {
+                current = (current.0 + dx, current.1 + dy);
+                if current != next_turn {
+                    positions.push(current);
+                }
+            }
+        }
+        if positions.len() > 1 && positions.first() == positions.last() {
+            positions.pop();
+        }
+        Some(GuardLoop { positions, turns })
+    }
 }
 
 fn parse_puzzle(input: &str) -> (i32, i32, Position, Obstacles) {
-    let width = (input.find('\n').unwrap() - 1) as i32;
-    let height = input.len() as i32 / (width + 1);
+    let width = input.lines().next().unwrap().trim_end().len() as i32;
+    let height = input
+        .lines()
+        .filter(|line| !line.trim_end().is_empty())
+        .count() as i32;
 
     let mut obstacles: Obstacles = HashSet::new();
     let mut guard_position: (i32, i32) = (-1, -1);
@@ -160,4 +395,80 @@ mod tests {
         let s = solve_part_two(width, height, &position, &obstacles);
         assert_eq!(s.unwrap().to_string(), "6")
     }
+
+    const LAB: &str = "....#.....
+.........#
+..........
+..#.......
+.......#..
+..........
+.#..^.....
+........#.
+#.........
+......#...
+";
+
+    #[test]
+    fn test_jump_table() {
+        let (width, height, position, obstacles) = parse_puzzle(LAB);
+        assert_eq!((width, height, position), (10, 10, (4, 6)));
+        assert_eq!(solve_part_one(width, height, &position, &obstacles), Ok(41));
+        assert_eq!(solve_part_two(width, height, &position, &obstacles), Ok(6));
+
+        let lab = JumpTable::new(width, height, &obstacles);
+        assert_eq!(
+            lab.stops[Direction::North.index()][lab.index(&(4, 6))],
+            Some((4, 1))
+        );
+        assert_eq!(
+            lab.stops[Direction::West.index()][lab.index(&(4, 6))],
+            Some((2, 6))
+        );
+        assert_eq!(
+            lab.stops[Direction::South.index()][lab.index(&(4, 6))],
+            None
+        );
+        assert_eq!(
+            lab.next_stop((4, 6), Direction::North, (4, 3)),
+            Some((4, 4))
+        );
+        assert_eq!(
+            lab.next_stop((4, 6), Direction::North, (4, 0)),
+            Some((4, 1))
+        );
+        assert_eq!(
+            lab.next_stop((4, 6), Direction::South, (4, 9)),
+            Some((4, 8))
+        );
+    }
+
+    #[test]
+    fn test_find_loop() {
+        let (width, height, position, obstacles) = parse_puzzle(LAB);
+        let lab = JumpTable::new(width, height, &obstacles);
+        assert_eq!(lab.find_loop(&position, &(0, 0)), None);
+
+        // Obstacle next to the guard's starting position
+        let guard_loop = lab.find_loop(&position, &(3, 6)).unwrap();
+        assert_eq!(
+            guard_loop.turns,
+            vec![
+                ((4, 1), Direction::East),
+                ((8, 1), Direction::South),
+                ((8, 6), Direction::West),
+                ((4, 6), Direction::North),
+            ]
+        );
+        assert_eq!(guard_loop.positions.len(), 18);
+        assert_eq!(guard_loop.positions[0], (4, 1));
+        assert!(guard_loop.positions.contains(&(6, 6)));
+
+        // A dead end at both ends of a corridor, the guard turns around twice per lap
+        let (width, height, position, obstacles) =
+            parse_puzzle(".#...\n..#..\n.^...\n#....\n.....");
+        let lab = JumpTable::new(width, height, &obstacles);
+        let guard_loop = lab.find_loop(&position, &(1, 4)).unwrap();
+        assert_eq!(guard_loop.turns.len(), 4);
+        assert_eq!(guard_loop.positions, vec![(1, 1), (1, 2), (1, 3), (1, 2)]);
+    }
 }
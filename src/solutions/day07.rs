@@ -1,6 +1,6 @@
 type Equation = (i64, Vec<i64>);
 type Puzzle = Vec<Equation>;
-type Operation = fn(i64, i64) -> i64;
+
 pub fn solve(puzzle_input_path: &str) -> (String, String) {
     let input = std::fs::read_to_string(puzzle_input_path).unwrap();
     let puzzle = parse_puzzle(&input);
@@ -9,55 +9,137 @@ pub fn solve(puzzle_input_path: &str) -> (String, String) {
     (solution_one, solution_two)
 }
 
-fn solve_part_one(puzzle: &Puzzle) -> String {
-    let mut solution = 0;
-    let op: Vec<Operation> = vec![|x, y| x * y, |x, y| x + y];
-    for equation in puzzle {
-        if is_equation_solvable(&equation, &op) {
-            solution += equation.0;
-        }
-    }
-    solution.to_string()
+// Operators are applied strictly left to right. `undo` returns the left operand that results
+// in `target` together with the right operand, or `None` if there is none.
+struct Operator {
+    symbol: &'static str,
+    apply: fn(i64, i64) -> Option<i64>,
+    undo: fn(i64, i64) -> Option<i64>,
 }
 
-fn is_equation_solvable((solution, values): &Equation, ops: &Vec<Operation>) -> bool {
-    if values.len() == 1 {
-        return values[0] == *solution;
-    }
+// All operators assume positive values, so that no operation reduces the result size. A zero
+// would make multiplication irreversible.
+const ADD: Operator = Operator {
+    symbol: "+",
+    apply: |x, y| x.checked_add(y),
+    undo: |target, y| (target >= y).then(|| target - y),
+};
 
-    // Optimization: assumes that there are no operations which reduce the result size
-    if values[0] > *solution {
-        return false;
-    }
+const MULTIPLY: Operator = Operator {
+    symbol: "*",
+    apply: |x, y| x.checked_mul(y),
+    undo: |target, y| (y != 0 && target % y == 0).then(|| target / y),
+};
 
-    for operation in ops {
-        let result = operation(values[0], values[1]);
+const CONCAT: Operator = Operator {
+    symbol: "||",
+    apply: |x, y| x.checked_mul(digit_shift(y)?)?.checked_add(y),
+    undo: |target, y| {
+        let shift = digit_shift(y)?;
+        (target >= y && target % shift == y).then(|| target / shift)
+    },
+};
 
-        let mut reduced_values: Vec<i64> = Vec::new();
-        reduced_values.push(result);
-        values.iter().skip(2).for_each(|v| reduced_values.push(*v));
-        let recursive_result = is_equation_solvable(&(*solution, reduced_values), ops);
-        if recursive_result {
-            return true;
+// Smallest power of ten greater than `value`, e.g. 100 for 42 and 10 for 0. `None` if it
+// doesn't fit into an i64.
+fn digit_shift(value: i64) -> Option<i64> {
+    let mut shift: i64 = 10;
+    while shift <= value {
+        shift = shift.checked_mul(10)?;
+    }
+    Some(shift)
+}
+
+// Shows how every equation is solved with the operators of part two, each solution is checked
+// by evaluating it forwards
+pub fn explain(puzzle_input_path: &str) -> Result<String, String> {
+    let input = std::fs::read_to_string(puzzle_input_path).map_err(|e| e.to_string())?;
+    let ops = [MULTIPLY, ADD, CONCAT];
+    let mut lines: Vec<String> = vec![];
+    for equation in parse_puzzle(&input) {
+        let Some(operators) = find_operators(&equation, &ops) else {
+            lines.push(format!("{}: {:?} can't be solved", equation.0, equation.1));
+            continue;
+        };
+        if evaluate(&equation.1, &operators) != Some(equation.0) {
+            return Err(format!("Wrong operators found for {}", equation.0));
         }
+        lines.push(format_equation(&equation, &operators));
     }
-    false
+    Ok(lines.join("\n"))
+}
+
+fn solve_part_one(puzzle: &Puzzle) -> String {
+    sum_solvable_equations(puzzle, &[MULTIPLY, ADD]).to_string()
 }
 
 fn solve_part_two(puzzle: &Puzzle) -> String {
-    let mut solution = 0;
-    let op: Vec<fn(i64, i64) -> i64> = vec![|x, y| x * y, |x, y| x + y, |x, y| {
-        let mut result_concat = String::new();
-        result_concat.push_str(x.to_string().as_str());
-        result_concat.push_str(y.to_string().as_str());
-        result_concat.parse::<i64>().unwrap()
-    }];
-    for equation in puzzle {
-        if is_equation_solvable(&equation, &op) {
-            solution += equation.0;
+    sum_solvable_equations(puzzle, &[MULTIPLY, ADD, CONCAT]).to_string()
+}
+
+fn sum_solvable_equations(puzzle: &Puzzle, ops: &[Operator]) -> i64 {
+    puzzle
+        .iter()
+        .filter(|equation| is_equation_solvable(equation, ops))
+        .map(|(solution, _)| solution)
+        .sum()
+}
+
+fn is_equation_solvable(equation: &Equation, ops: &[Operator]) -> bool {
+    find_operators(equation, ops).is_some()
+}
+
+// Returns the operators between the values that produce the solution. The equation is solved
+// backwards, starting with the last value every operator is undone until only the first one
+// is left. Undoing fails early for most operators, which prunes the search a lot.
+fn find_operators<'a>(
+    (solution, values): &Equation,
+    ops: &'a [Operator],
+) -> Option<Vec<&'a Operator>> {
+    fn undo_last<'a>(
+        target: i64,
+        values: &[i64],
+        ops: &'a [Operator],
+        assignment: &mut Vec<&'a Operator>,
+    ) -> bool {
+        let (last, rest) = values.split_last().unwrap();
+        if rest.is_empty() {
+            return *last == target;
         }
+        for operator in ops {
+            if let Some(left) = (operator.undo)(target, *last) {
+                if undo_last(left, rest, ops, assignment) {
+                    assignment.push(operator);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    if values.is_empty() {
+        return None;
+    }
+    let mut assignment: Vec<&Operator> = Vec::with_capacity(values.len() - 1);
+    undo_last(*solution, values, ops, &mut assignment).then_some(assignment)
+}
+
+// Applies the operators left to right, `None` on overflow
+fn evaluate(values: &[i64], operators: &[&Operator]) -> Option<i64> {
+    let mut result = *values.first()?;
+    for (operator, value) in operators.iter().zip(values.iter().skip(1)) {
+        result = (operator.apply)(result, *value)?;
+    }
+    Some(result)
+}
+
+// Renders a solved equation like `3267 = 81 + 40 * 27`
+fn format_equation((solution, values): &Equation, operators: &[&Operator]) -> String {
+    let mut expression = format!("{} = {}", solution, values[0]);
+    for (operator, value) in operators.iter().zip(values.iter().skip(1)) {
+        expression.push_str(&format!(" {} {}", operator.symbol, value));
     }
-    solution.to_string()
+    expression
 }
 
 fn parse_puzzle(input: &str) -> Puzzle {
@@ -81,6 +163,11 @@ fn parse_puzzle(input: &str) -> Puzzle {
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    fn format_solution(equation: &Equation, ops: &[Operator]) -> Option<String> {
+        let operators = find_operators(equation, ops)?;
+        Some(format_equation(equation, &operators))
+    }
     #[test]
     fn test_solve_part_one() {
         let input = std::fs::read_to_string("./resources/day07/example.txt").unwrap();
@@ -96,4 +183,53 @@ mod tests {
         let solution = solve_part_two(&puzzle);
         assert_eq!(solution, "11387".to_string());
     }
+
+    #[test]
+    fn test_reverse_solver() {
+        let input = "190: 10 19\n3267: 81 40 27\n83: 17 5\n156: 15 6\n7290: 6 8 6 15\n161011: 16 10 13\n192: 17 8 14\n21037: 9 7 18 13\n292: 11 6 16 20";
+        let puzzle = parse_puzzle(input);
+        assert_eq!(solve_part_one(&puzzle), "3749");
+        assert_eq!(solve_part_two(&puzzle), "11387");
+
+        let ops = [MULTIPLY, ADD, CONCAT];
+        assert_eq!(
+            format_solution(&puzzle[0], &ops),
+            Some("190 = 10 * 19".to_string())
+        );
+        assert_eq!(
+            format_solution(&puzzle[4], &ops),
+            Some("7290 = 6 * 8 || 6 * 15".to_string())
+        );
+        assert_eq!(format_solution(&puzzle[2], &ops), None);
+        for equation in &puzzle {
+            if let Some(operators) = find_operators(equation, &ops) {
+                assert_eq!(evaluate(&equation.1, &operators), Some(equation.0));
+            }
+        }
+
+        // User defined operator set
+        let subtract = Operator {
+            symbol: "-",
+            apply: |x, y| x.checked_sub(y),
+            undo: |target, y| target.checked_add(y),
+        };
+        let equation: Equation = (4, vec![10, 3, 3]);
+        assert!(!is_equation_solvable(&equation, &[ADD, MULTIPLY]));
+        assert_eq!(
+            format_solution(&equation, &[ADD, subtract]),
+            Some("4 = 10 - 3 - 3".to_string())
+        );
+        assert_eq!(
+            format_solution(&(100, vec![1, 0, 0]), &[CONCAT]),
+            Some("100 = 1 || 0 || 0".to_string())
+        );
+
+        // Values with 19 digits have no power of ten above them in an i64
+        assert_eq!(digit_shift(999_999_999_999_999_999), Some(10_i64.pow(18)));
+        assert_eq!(digit_shift(10_i64.pow(18)), None);
+        let equation: Equation = (10_i64.pow(18) + 1, vec![1, 10_i64.pow(18)]);
+        assert!(is_equation_solvable(&equation, &[ADD, CONCAT]));
+        assert!(!is_equation_solvable(&equation, &[CONCAT]));
+        assert_eq!((CONCAT.apply)(1, i64::MAX), None);
+    }
 }
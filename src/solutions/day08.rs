@@ -12,103 +12,239 @@ pub fn solve(input_file_path: &str) -> (String, String) {
     (solution_part_one, solution_part_two)
 }
 
-fn solve_part_one((width, height): FieldSize, antennas: &Antennas) -> i32 {
-    let mut anti_nodes_all: HashSet<Position> = HashSet::new();
-    let out_of_bound = |(x, y): &Position| -> bool {
-        return *x < 0 || *y < 0 || *x >= width || *y >= height;
-    };
-
-    for (_, positions) in antennas {
-        for i in 0..positions.len() - 1 {
-            for j in (i + 1)..positions.len() {
-                let anti_nodes_signal = get_anti_nodes(positions[i], positions[j], &out_of_bound);
-                anti_nodes_all.extend(anti_nodes_signal);
-            }
+// Draws the anti-nodes of both parts and counts them per frequency
+pub fn explain(input_file_path: &str) -> Result<String, String> {
+    let input = std::fs::read_to_string(input_file_path).map_err(|e| e.to_string())?;
+    let (grid_size, antennas) = parse_puzzle(&input);
+    let mut lines: Vec<String> = vec![];
+    for (part, model) in [AntiNodeModel::Ratio(2, 1), AntiNodeModel::Resonance]
+        .iter()
+        .enumerate()
+    {
+        let anti_nodes = find_anti_nodes(grid_size, &antennas, *model);
+        lines.push(format!(
+            "Part {}: {} anti-nodes",
+            part + 1,
+            anti_nodes.len()
+        ));
+        lines.push(render_map(grid_size, &antennas, &anti_nodes));
+        for stats in get_frequency_stats(grid_size, &antennas, *model) {
+            lines.push(format!(
+                "{}: {} antennas, {} pairs, {} anti-nodes, {} of them exclusive",
+                stats.signal,
+                stats.antennas,
+                stats.pairs,
+                stats.anti_nodes,
+                stats.exclusive_anti_nodes
+            ));
         }
     }
+    Ok(lines.join("\n"))
+}
 
-    anti_nodes_all.len() as i32
+fn solve_part_one(grid_size: FieldSize, antennas: &Antennas) -> i32 {
+    find_anti_nodes(grid_size, antennas, AntiNodeModel::Ratio(2, 1)).len() as i32
 }
 
-fn solve_part_two((width, height): FieldSize, antennas: &Antennas) -> i32 {
-    // Code duplication but im too lazy ¯\_(ツ)_/¯
-    let mut anti_nodes_all: HashSet<Position> = HashSet::new();
-    let out_of_bound = |(x, y): &Position| -> bool {
-        return *x < 0 || *y < 0 || *x >= width || *y >= height;
-    };
-
-    for (_, positions) in antennas {
-        for i in 0..positions.len() - 1 {
-            for j in (i + 1)..positions.len() {
-                let anti_nodes_signal = get_anti_nodes(positions[i], positions[j], &out_of_bound);
-                let anti_nodes_resonance =
-                    get_anti_nodes_resonance(positions[i], positions[j], &out_of_bound);
-                anti_nodes_all.extend(anti_nodes_signal);
-                anti_nodes_all.extend(anti_nodes_resonance);
-            }
+fn solve_part_two(grid_size: FieldSize, antennas: &Antennas) -> i32 {
+    find_anti_nodes(grid_size, antennas, AntiNodeModel::Resonance).len() as i32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AntiNodeModel {
+    // Points on the line through both antennas that are p times as far away from one antenna
+    // as from the other one
+    Ratio(u32, u32),
+    // Every point on the line through both antennas
+    Resonance,
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// Exact fraction with a positive denominator in lowest terms
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Fraction {
+    numerator: i64,
+    denominator: i64,
+}
+
+impl Fraction {
+    fn new(numerator: i64, denominator: i64) -> Fraction {
+        let divisor = gcd(numerator, denominator) * denominator.signum();
+        Fraction {
+            numerator: numerator / divisor,
+            denominator: denominator / divisor,
         }
     }
+}
 
-    anti_nodes_all.len() as i32
+fn find_anti_nodes(
+    (width, height): FieldSize,
+    antennas: &Antennas,
+    model: AntiNodeModel,
+) -> HashSet<Position> {
+    let in_bounds = |(x, y): &Position| *x >= 0 && *y >= 0 && *x < width && *y < height;
+    let mut anti_nodes_all: HashSet<Position> = HashSet::new();
+    for positions in antennas.values() {
+        anti_nodes_all.extend(get_frequency_anti_nodes(positions, model, in_bounds));
+    }
+    anti_nodes_all
 }
 
-fn get_anti_nodes(
-    antenna_1: Position,
-    antenna_2: Position,
-    out_of_bound: impl Fn(&Position) -> bool,
+fn get_frequency_anti_nodes(
+    positions: &[Position],
+    model: AntiNodeModel,
+    in_bounds: impl Fn(&Position) -> bool,
 ) -> HashSet<Position> {
-    let (a1_x, a1_y) = antenna_1;
-    let (a2_x, a2_y) = antenna_2;
-    let mut positions: HashSet<Position> = HashSet::new();
-    let (diff_x, diff_y) = (antenna_2.0 - antenna_1.0, antenna_2.1 - antenna_1.1);
+    let mut anti_nodes: HashSet<Position> = HashSet::new();
+    for i in 0..positions.len() {
+        for j in (i + 1)..positions.len() {
+            let anti_nodes_pair = match model {
+                AntiNodeModel::Ratio(p, q) => get_anti_nodes(positions[i], positions[j], p, q),
+                AntiNodeModel::Resonance => {
+                    get_anti_nodes_resonance(positions[i], positions[j], &in_bounds)
+                }
+            };
+            anti_nodes.extend(anti_nodes_pair.into_iter().filter(&in_bounds));
+        }
+    }
+    anti_nodes
+}
 
-    // Check and add inner anti_nodes
-    if diff_x % 3 == 0 && diff_y % 3 == 0 {
-        let (diff_x, diff_y) = (diff_x / 3, diff_y / 3);
-        let anti_node_1 = (a1_x + diff_x, a1_y + diff_y);
-        let anti_node_2 = (a2_x - diff_x, a2_y - diff_y);
-        positions.insert(anti_node_1);
-        positions.insert(anti_node_2);
-    }
-    // Add outer anti_nodes
-    let anti_node_3 = (a2_x + diff_x, a2_y + diff_y);
-    let anti_node_4 = (a1_x - diff_x, a1_y - diff_y);
-    positions.insert(anti_node_3);
-    positions.insert(anti_node_4);
-
-    positions.retain(|pos| !out_of_bound(pos));
-    positions
+// Every point of the line is antenna_1 + t * (antenna_2 - antenna_1). The distances to the
+// antennas have the ratio p : q or q : p for t = p / (p - q), p / (p + q) and the same with p
+// and q swapped. Only fractions of the difference that land on the grid are kept.
+fn get_anti_nodes(antenna_1: Position, antenna_2: Position, p: u32, q: u32) -> Vec<Position> {
+    if p == 0 && q == 0 {
+        return vec![];
+    }
+    let (p, q) = (p as i64, q as i64);
+    let (diff_x, diff_y) = (
+        (antenna_2.0 - antenna_1.0) as i64,
+        (antenna_2.1 - antenna_1.1) as i64,
+    );
+
+    let mut factors: Vec<Fraction> = vec![Fraction::new(p, p + q), Fraction::new(q, p + q)];
+    if p != q {
+        factors.push(Fraction::new(p, p - q));
+        factors.push(Fraction::new(q, q - p));
+    }
+    factors.sort_by_key(|t| (t.numerator, t.denominator));
+    factors.dedup();
+
+    factors
+        .into_iter()
+        .filter(|t| diff_x % t.denominator == 0 && diff_y % t.denominator == 0)
+        .map(|t| {
+            (
+                antenna_1.0 + (diff_x / t.denominator * t.numerator) as i32,
+                antenna_1.1 + (diff_y / t.denominator * t.numerator) as i32,
+            )
+        })
+        .collect()
 }
 
+// Walks the line in the smallest steps that hit grid points, so points between the antennas
+// are included as well
 fn get_anti_nodes_resonance(
     antenna_1: Position,
     antenna_2: Position,
-    out_of_bound: impl Fn(&Position) -> bool,
-) -> HashSet<Position> {
-    let mut positions: HashSet<Position> = HashSet::new();
+    in_bounds: impl Fn(&Position) -> bool,
+) -> Vec<Position> {
     let (diff_x, diff_y) = (antenna_2.0 - antenna_1.0, antenna_2.1 - antenna_1.1);
+    let divisor = gcd(diff_x as i64, diff_y as i64) as i32;
+    if divisor == 0 {
+        return vec![antenna_1];
+    }
+    let (step_x, step_y) = (diff_x / divisor, diff_y / divisor);
 
-    positions.insert(antenna_1);
-    positions.insert(antenna_2);
-
-    let mut resonate_pos = antenna_1;
-    loop {
-        resonate_pos = (resonate_pos.0 + diff_x, resonate_pos.1 + diff_y);
-        if out_of_bound(&resonate_pos) {
-            break;
+    let mut positions: Vec<Position> = vec![antenna_1];
+    for direction in [1, -1] {
+        let mut resonate_pos = antenna_1;
+        loop {
+            resonate_pos = (
+                resonate_pos.0 + direction * step_x,
+                resonate_pos.1 + direction * step_y,
+            );
+            if !in_bounds(&resonate_pos) {
+                break;
+            }
+            positions.push(resonate_pos);
         }
-        positions.insert(resonate_pos);
+    }
+    positions
+}
+
+#[derive(Debug, PartialEq)]
+struct FrequencyStats {
+    signal: Signal,
+    antennas: usize,
+    pairs: usize,
+    anti_nodes: usize,
+    // Anti-nodes that no other frequency produces
+    exclusive_anti_nodes: usize,
+}
+
+fn get_frequency_stats(
+    (width, height): FieldSize,
+    antennas: &Antennas,
+    model: AntiNodeModel,
+) -> Vec<FrequencyStats> {
+    let in_bounds = |(x, y): &Position| *x >= 0 && *y >= 0 && *x < width && *y < height;
+    let mut signals = antennas.keys().copied().collect::<Vec<Signal>>();
+    signals.sort();
+    let anti_nodes = signals
+        .iter()
+        .map(|signal| get_frequency_anti_nodes(&antennas[signal], model, in_bounds))
+        .collect::<Vec<HashSet<Position>>>();
+
+    let mut producers: HashMap<Position, usize> = HashMap::new();
+    for position in anti_nodes.iter().flatten() {
+        *producers.entry(*position).or_default() += 1;
     }
 
-    let mut resonate_pos = antenna_2;
-    loop {
-        resonate_pos = (resonate_pos.0 - diff_x, resonate_pos.1 - diff_y);
-        if out_of_bound(&resonate_pos) {
-            break;
+    signals
+        .iter()
+        .zip(anti_nodes.iter())
+        .map(|(signal, anti_nodes)| {
+            let count = antennas[signal].len();
+            FrequencyStats {
+                signal: *signal,
+                antennas: count,
+                pairs: count * count.saturating_sub(1) / 2,
+                anti_nodes: anti_nodes.len(),
+                exclusive_anti_nodes: anti_nodes.iter().filter(|p| producers[*p] == 1).count(),
+            }
+        })
+        .collect()
+}
+
+// Draws the map like the puzzle description, anti-nodes are marked with '#' unless there
+// already is an antenna
+fn render_map(
+    (width, height): FieldSize,
+    antennas: &Antennas,
+    anti_nodes: &HashSet<Position>,
+) -> String {
+    let mut grid: Vec<Vec<char>> = vec![vec!['.'; width as usize]; height as usize];
+    for (x, y) in anti_nodes {
+        grid[*y as usize][*x as usize] = '#';
+    }
+    for (signal, positions) in antennas {
+        for (x, y) in positions {
+            grid[*y as usize][*x as usize] = *signal;
         }
-        positions.insert(resonate_pos);
     }
-    positions
+    grid.iter()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
 }
 
 fn parse_puzzle(input: &str) -> (FieldSize, Antennas) {
@@ -146,4 +282,61 @@ mod tests {
         let solution = solve_part_two(grid_size, &an).to_string();
         assert_eq!(solution, "34");
     }
+
+    const EXAMPLE: &str = "............\n........0...\n.....0......\n.......0....\n....0.......\n......A.....\n............\n............\n........A...\n.........A..\n............\n............";
+
+    #[test]
+    fn test_anti_nodes() {
+        let (grid_size, antennas) = parse_puzzle(EXAMPLE);
+        assert_eq!(solve_part_one(grid_size, &antennas), 14);
+        assert_eq!(solve_part_two(grid_size, &antennas), 34);
+
+        let (grid_size, antennas) = parse_puzzle("T.........\n...T......\n.T........\n..........\n..........\n..........\n..........\n..........\n..........\n..........");
+        let anti_nodes = find_anti_nodes(grid_size, &antennas, AntiNodeModel::Resonance);
+        assert_eq!(anti_nodes.len(), 9);
+        assert_eq!(
+            render_map(grid_size, &antennas, &anti_nodes),
+            "T....#....\n...T......\n.T....#...\n.........#\n..#.......\n..........\n...#......\n..........\n....#.....\n.........."
+        );
+
+        // Antennas two steps apart in both directions have a grid point between them
+        assert_eq!(
+            get_anti_nodes_resonance((0, 0), (4, 2), |(x, y)| *x >= 0
+                && *y >= 0
+                && *x < 7
+                && *y < 4),
+            vec![(0, 0), (2, 1), (4, 2), (6, 3)]
+        );
+        // Thrice as far from one antenna as from the other
+        let mut anti_nodes = get_anti_nodes((0, 0), (8, 0), 3, 1);
+        anti_nodes.sort();
+        assert_eq!(anti_nodes, vec![(-4, 0), (2, 0), (6, 0), (12, 0)]);
+        assert_eq!(get_anti_nodes((0, 0), (3, 0), 1, 1), vec![]);
+        assert_eq!(get_anti_nodes((0, 0), (2, 4), 1, 1), vec![(1, 2)]);
+    }
+
+    #[test]
+    fn test_frequency_stats() {
+        let (grid_size, antennas) = parse_puzzle(EXAMPLE);
+        let stats = get_frequency_stats(grid_size, &antennas, AntiNodeModel::Ratio(2, 1));
+        assert_eq!(
+            stats,
+            vec![
+                FrequencyStats {
+                    signal: '0',
+                    antennas: 4,
+                    pairs: 6,
+                    anti_nodes: 10,
+                    exclusive_anti_nodes: 9
+                },
+                FrequencyStats {
+                    signal: 'A',
+                    antennas: 3,
+                    pairs: 3,
+                    anti_nodes: 5,
+                    exclusive_anti_nodes: 4
+                },
+            ]
+        );
+    }
 }
@@ -1,136 +1,234 @@
-use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 
 pub fn solve(input_file_path: &str) -> (String, String) {
     let input = std::fs::read_to_string(input_file_path).unwrap();
     let puzzle_input = parse_puzzle_input(&input);
-    let solution_part_one = solve_part_one(puzzle_input.clone()).to_string();
-    let solution_part_two = solve_part_two(puzzle_input.clone()).to_string();
-    (solution_part_one.to_string(), solution_part_two.to_string())
+    let solution_part_one = solve_part_one(&puzzle_input).to_string();
+    let solution_part_two = solve_part_two(&puzzle_input).to_string();
+    (solution_part_one, solution_part_two)
 }
 
-fn solve_part_one(mut puzzle: Vec<i32>) -> i64 {
-    let mut left_index = 0;
-    let mut right_index = puzzle.len() - 1;
-
-    loop {
-        if right_index <= left_index {
-            break;
+// Compares the checksums of all compaction strategies. Disks short enough to fit on a line are
+// drawn as well.
+pub fn explain(input_file_path: &str) -> Result<String, String> {
+    let input = std::fs::read_to_string(input_file_path).map_err(|e| e.to_string())?;
+    let disk = parse_puzzle_input(&input);
+    let is_drawn = disk.size <= 120;
+    let mut lines: Vec<String> = vec![];
+    if is_drawn {
+        lines.push("Before compaction:".to_string());
+        lines.push(disk.render());
+    }
+    for strategy in [
+        CompactionStrategy::Blockwise,
+        CompactionStrategy::FirstFit,
+        CompactionStrategy::BestFit,
+        CompactionStrategy::DefragmentToEnd,
+    ] {
+        let compacted = disk.compact(strategy);
+        lines.push(format!(
+            "{:?}: checksum {}",
+            strategy,
+            calc_checksum(&compacted)
+        ));
+        if is_drawn {
+            lines.push(compacted.render());
         }
+    }
+    Ok(lines.join("\n"))
+}
 
-        if puzzle[left_index] != -1 {
-            left_index += 1;
-            continue;
-        }
+fn solve_part_one(disk: &Disk) -> i64 {
+    calc_checksum(&disk.compact(CompactionStrategy::Blockwise))
+}
 
-        if puzzle[right_index] == -1 {
-            right_index -= 1;
-            continue;
-        }
+fn solve_part_two(disk: &Disk) -> i64 {
+    calc_checksum(&disk.compact(CompactionStrategy::FirstFit))
+}
 
-        puzzle.swap(left_index, right_index);
-        left_index += 1;
-        right_index -= 1;
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompactionStrategy {
+    // Moves single blocks from the end of the disk into the leftmost free block
+    Blockwise,
+    // Moves every file, highest id first, into the leftmost free span it fits in
+    FirstFit,
+    // Moves every file, highest id first, into the smallest free span left of it
+    BestFit,
+    // Packs all files in their current order to the start of the disk, so that all the free
+    // space ends up at the end
+    DefragmentToEnd,
+}
 
-    calc_checksum(&puzzle)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileSpan {
+    id: usize,
+    start: usize,
+    length: usize,
 }
 
-fn solve_part_two(mut puzzle: Vec<i32>) -> i64 {
-    let mut file_end_index = puzzle.len() - 1;
-    let mut file_start_index: usize;
+// Run-length representation of the disk. A file may consist of multiple spans after
+// a blockwise compaction. Everything not covered by a file span is free space.
+#[derive(Debug, Clone)]
+struct Disk {
+    files: Vec<FileSpan>,
+    size: usize,
+}
 
-    let mut visited_file_blocks: HashSet<i32> = HashSet::new();
+type FreeSpan = (usize, usize);
 
-    'outer: loop {
-        // Find start and end index of a data blocks
-        while file_end_index > 0 && puzzle[file_end_index] == -1 {
-            file_end_index -= 1;
+impl Disk {
+    fn compact(&self, strategy: CompactionStrategy) -> Disk {
+        let mut files = self.files.clone();
+        files.sort_by_key(|file| file.start);
+        let files = match strategy {
+            CompactionStrategy::Blockwise => self.compact_blockwise(files),
+            CompactionStrategy::FirstFit => self.compact_whole_files(files, false),
+            CompactionStrategy::BestFit => self.compact_whole_files(files, true),
+            CompactionStrategy::DefragmentToEnd => {
+                let mut next_start = 0;
+                for file in files.iter_mut() {
+                    file.start = next_start;
+                    next_start += file.length;
+                }
+                files
+            }
+        };
+        Disk {
+            files,
+            size: self.size,
         }
-        file_start_index = file_end_index;
-        while file_start_index > 0 && puzzle[file_start_index - 1] == puzzle[file_start_index] {
-            file_start_index -= 1;
+    }
+
+    // Free spans in order of their position, `files` has to be sorted by start
+    fn free_spans(&self, files: &[FileSpan]) -> Vec<FreeSpan> {
+        let mut free: Vec<FreeSpan> = vec![];
+        let mut position = 0;
+        for file in files {
+            if file.start > position {
+                free.push((position, file.start - position));
+            }
+            position = file.start + file.length;
         }
-        let file_block_size = file_end_index - file_start_index + 1;
-
-        // Ignore data blocks that already have been visited
-        if visited_file_blocks.contains(&puzzle[file_start_index]) {
-            file_end_index -= file_block_size;
-            continue 'outer;
-        } else {
-            visited_file_blocks.insert(puzzle[file_start_index]);
+        if position < self.size {
+            free.push((position, self.size - position));
         }
+        free
+    }
 
-        // Match first free
-        let mut free_start_index = 0;
-        let mut free_end_index: usize;
-        'find_free_first: loop {
-            while free_start_index < file_start_index && puzzle[free_start_index] != -1 {
-                free_start_index += 1;
-            }
-            free_end_index = free_start_index;
-            while free_end_index < file_start_index && puzzle[free_end_index + 1] == -1 {
-                free_end_index += 1;
+    fn compact_blockwise(&self, mut files: Vec<FileSpan>) -> Vec<FileSpan> {
+        let mut moved: Vec<FileSpan> = vec![];
+        for (mut free_start, mut free_length) in self.free_spans(&files) {
+            while free_length > 0 {
+                let Some(last) = files.last_mut() else {
+                    break;
+                };
+                if last.start < free_start {
+                    break;
+                }
+                let block_count = free_length.min(last.length);
+                moved.push(FileSpan {
+                    id: last.id,
+                    start: free_start,
+                    length: block_count,
+                });
+                last.length -= block_count;
+                if last.length == 0 {
+                    files.pop();
+                }
+                free_start += block_count;
+                free_length -= block_count;
             }
-            let free_size = free_end_index - free_start_index + 1;
+        }
+        files.extend(moved);
+        files.sort_by_key(|file| file.start);
+        files
+    }
 
-            // Check if space is large enough and swap in case of
-            if free_size >= file_block_size {
-                for swap_index in 0..file_block_size {
-                    puzzle.swap(free_start_index + swap_index, file_start_index + swap_index);
-                }
-                break 'find_free_first;
+    // Free spans are kept in one min-heap of start positions per span length. The leftmost
+    // span a file fits in is the smallest top of all heaps with a length >= the file length.
+    fn compact_whole_files(&self, mut files: Vec<FileSpan>, best_fit: bool) -> Vec<FileSpan> {
+        let free_spans = self.free_spans(&files);
+        let max_length = free_spans.iter().map(|&(_, l)| l).max().unwrap_or(0);
+        let mut free_heaps: Vec<BinaryHeap<Reverse<usize>>> =
+            vec![BinaryHeap::new(); max_length + 1];
+        for (start, length) in free_spans {
+            free_heaps[length].push(Reverse(start));
+        }
+
+        let mut order = (0..files.len()).collect::<Vec<usize>>();
+        order.sort_by_key(|&i| Reverse(files[i].id));
+        for file_index in order {
+            let file = files[file_index];
+            let candidates = (file.length..=max_length).filter_map(|length| {
+                free_heaps[length]
+                    .peek()
+                    .filter(|Reverse(start)| *start < file.start)
+                    .map(|Reverse(start)| (length, *start))
+            });
+            let target = if best_fit {
+                candidates.min_by_key(|&(length, start)| (length, start))
             } else {
-                free_start_index += free_size;
-            }
+                candidates.min_by_key(|&(_, start)| start)
+            };
 
-            if free_start_index >= file_start_index || file_start_index == 0 && file_block_size == 0
-            {
-                break 'find_free_first;
+            if let Some((length, start)) = target {
+                free_heaps[length].pop();
+                if length > file.length {
+                    free_heaps[length - file.length].push(Reverse(start + file.length));
+                }
+                files[file_index].start = start;
             }
         }
+        files.sort_by_key(|file| file.start);
+        files
+    }
 
-        if file_end_index <= file_block_size {
-            break;
+    // Renders the disk like the puzzle description, e.g. `00...111...2...333.44.5555`.
+    // Ids above 9 continue with letters and wrap around after 'z'.
+    fn render(&self) -> String {
+        let mut output = vec!['.'; self.size];
+        for file in &self.files {
+            let symbol = char::from_digit((file.id % 36) as u32, 36).unwrap();
+            output[file.start..file.start + file.length].fill(symbol);
         }
-        file_end_index -= file_block_size;
+        output.into_iter().collect()
     }
-    calc_checksum(&puzzle)
 }
 
-fn calc_checksum(puzzle: &Vec<i32>) -> i64 {
-    let mut check_sum: i64 = 0;
-    for i in 0..puzzle.len() {
-        if puzzle[i] != -1 {
-            check_sum += puzzle[i] as i64 * i as i64;
-        }
-    }
-    check_sum
+fn calc_checksum(disk: &Disk) -> i64 {
+    disk.files
+        .iter()
+        .map(|file| {
+            let (start, length) = (file.start as i64, file.length as i64);
+            // Sum of the positions start..start + length
+            let position_sum = length * start + length * (length - 1) / 2;
+            file.id as i64 * position_sum
+        })
+        .sum()
 }
 
-fn parse_puzzle_input(input: &str) -> Vec<i32> {
+fn parse_puzzle_input(input: &str) -> Disk {
     let input = input.lines().next().unwrap();
-    let disk_size = input
-        .to_string()
-        .chars()
-        .map(|c| c.to_digit(10).unwrap())
-        .fold(0, |acc, byte| acc + byte);
-
-    let mut file_index = 0;
-    let mut output = Vec::with_capacity(disk_size as usize);
+
+    let mut files: Vec<FileSpan> = vec![];
+    let mut position = 0;
     for (index, digit) in input.chars().enumerate() {
+        let length = digit.to_digit(10).unwrap() as usize;
         let is_free_space = index % 2 != 0;
-        for _ in 0..digit.to_digit(10).unwrap() {
-            if is_free_space {
-                output.push(-1);
-            } else {
-                output.push(file_index);
-            }
-        }
-        if !is_free_space {
-            file_index += 1;
+        if !is_free_space && length > 0 {
+            files.push(FileSpan {
+                id: index / 2,
+                start: position,
+                length,
+            });
         }
+        position += length;
+    }
+    Disk {
+        files,
+        size: position,
     }
-    output
 }
 
 #[cfg(test)]
@@ -141,14 +239,49 @@ mod tests {
     fn test_solve_part_one() {
         let input = "2333133121414131402".to_ascii_lowercase();
         let puzzle_input = parse_puzzle_input(&input);
-        let solution = solve_part_one(puzzle_input.clone()).to_string();
+        let solution = solve_part_one(&puzzle_input).to_string();
         assert_eq!(solution, "1928");
     }
     #[test]
     fn test_solve_part_two() {
         let input = "2333133121414131402".to_ascii_lowercase();
         let puzzle_input = parse_puzzle_input(&input);
-        let solution = solve_part_two(puzzle_input.clone()).to_string();
+        let solution = solve_part_two(&puzzle_input).to_string();
         assert_eq!(solution, "2858");
     }
+
+    #[test]
+    fn test_strategies() {
+        let disk = parse_puzzle_input("2333133121414131402");
+        assert_eq!(disk.render(), "00...111...2...333.44.5555.6666.777.888899");
+
+        let compacted = disk.compact(CompactionStrategy::Blockwise);
+        assert_eq!(
+            compacted.render(),
+            "0099811188827773336446555566.............."
+        );
+
+        let compacted = disk.compact(CompactionStrategy::FirstFit);
+        assert_eq!(
+            compacted.render(),
+            "00992111777.44.333....5555.6666.....8888.."
+        );
+
+        let compacted = disk.compact(CompactionStrategy::DefragmentToEnd);
+        assert_eq!(
+            compacted.render(),
+            "0011123334455556666777888899.............."
+        );
+
+        let disk = parse_puzzle_input("1311121");
+        assert_eq!(disk.render(), "0...1.2..3");
+        assert_eq!(
+            disk.compact(CompactionStrategy::FirstFit).render(),
+            "0321......"
+        );
+        assert_eq!(
+            disk.compact(CompactionStrategy::BestFit).render(),
+            "021..3...."
+        );
+    }
 }
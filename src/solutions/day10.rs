@@ -1,8 +1,9 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
 
 type GridDimension = (i16, i16);
 type Position = (i16, i16);
 type Puzzle = (GridDimension, Vec<u8>);
+type TrailCount = u64;
 pub fn solve(input_file_path: &str) -> (String, String) {
     let input = std::fs::read_to_string(input_file_path).unwrap();
     let puzzle = parse_puzzle(&input);
@@ -10,70 +11,203 @@ pub fn solve(input_file_path: &str) -> (String, String) {
     (solution_part_one.to_string(), solution_part_two.to_string())
 }
 
-fn solve_both_parts(puzzle: &Puzzle) -> (u16, u16) {
-    let ((width, height), data) = puzzle;
-
-    let lookup_pos_value = |(x, y): &Position| -> u8 {
-        return data[(y * (*width) + x) as usize];
-    };
-
-    let is_out_of_bounds = |(x, y): &Position| -> bool {
-        return *x < 0 || *y < 0 || *x >= *width || *y >= *height;
-    };
-
-    let directions: Vec<Position> = vec![(1, 0), (0, -1), (-1, 0), (0, 1)];
-
-    let mut sum_trailhead_score: u16 = 0;
-    let mut sum_unique_paths: u16 = 0;
-
-    for y in 0..*height {
-        for x in 0..*width {
-            let start_pos = (x, y);
-            if lookup_pos_value(&start_pos) == '0' as u8 {
-                let mut reachable_ends: HashSet<Position> = HashSet::new();
-                let unique_paths = explore_hiking_path(
-                    start_pos,
-                    &directions,
-                    &lookup_pos_value,
-                    &is_out_of_bounds,
-                    &mut reachable_ends,
-                );
-                sum_unique_paths = sum_unique_paths + unique_paths;
-                sum_trailhead_score += reachable_ends.len() as u16;
-            }
+// Lists score and rating of every trailhead and draws a trail of the best rated one
+pub fn explain(input_file_path: &str) -> Result<String, String> {
+    let input = std::fs::read_to_string(input_file_path).map_err(|e| e.to_string())?;
+    let trail_map = TrailMap::new(&parse_puzzle(&input));
+    let config = TrailConfig::default();
+
+    let mut lines: Vec<String> = vec![];
+    let mut best: Option<(Position, TrailCount)> = None;
+    for trailhead in trail_map.trailheads(&config) {
+        let (score, rating) = trail_map.analyse_trailhead(trailhead, &config)?;
+        lines.push(format!(
+            "Trailhead {:?}: score {}, rating {}",
+            trailhead, score, rating
+        ));
+        if best.is_none_or(|(_, best_rating)| rating > best_rating) {
+            best = Some((trailhead, rating));
+        }
+    }
+
+    if let Some((trailhead, rating)) = best {
+        let trails = trail_map.enumerate_trails(trailhead, &config)?;
+        if trails.len() as TrailCount != rating {
+            return Err(format!(
+                "Found {} trails for rating {}",
+                trails.len(),
+                rating
+            ));
+        }
+        if let Some(trail) = trails.first() {
+            lines.push(format!(
+                "One of the {} trails from {:?}:",
+                rating, trailhead
+            ));
+            lines.push(trail_map.render_trail(trail));
         }
     }
+    Ok(lines.join("\n"))
+}
+
+fn solve_both_parts(puzzle: &Puzzle) -> (TrailCount, TrailCount) {
+    let trail_map = TrailMap::new(puzzle);
+    let config = TrailConfig::default();
+
+    let mut sum_trailhead_score: TrailCount = 0;
+    let mut sum_unique_paths: TrailCount = 0;
+    for trailhead in trail_map.trailheads(&config) {
+        let (score, rating) = trail_map.analyse_trailhead(trailhead, &config).unwrap();
+        sum_trailhead_score += score;
+        sum_unique_paths += rating;
+    }
     (sum_trailhead_score, sum_unique_paths)
 }
 
-fn explore_hiking_path(
-    start_pos: Position,
-    directions: &Vec<Position>,
-    lookup_pos_value: &impl Fn(&Position) -> u8,
-    is_out_of_bounds: &impl Fn(&Position) -> bool,
-    reachable_ends: &mut HashSet<Position>,
-) -> u16 {
-    let current_pos_value = lookup_pos_value(&start_pos);
-    if current_pos_value == '9' as u8 {
-        reachable_ends.insert(start_pos);
-        return 1;
+// Trails start at `start_height` and change by exactly `step` per move until `end_height`
+#[derive(Debug, Clone, Copy)]
+struct TrailConfig {
+    start_height: u8,
+    end_height: u8,
+    step: i8,
+}
+
+impl Default for TrailConfig {
+    fn default() -> Self {
+        TrailConfig {
+            start_height: 0,
+            end_height: 9,
+            step: 1,
+        }
+    }
+}
+
+impl TrailConfig {
+    fn length(&self) -> Result<usize, String> {
+        let climb = self.end_height as i16 - self.start_height as i16;
+        let step = self.step as i16;
+        if step == 0 || climb % step != 0 || climb / step < 0 {
+            return Err(format!(
+                "Height {} can't be reached from {} in steps of {}",
+                self.end_height, self.start_height, self.step
+            ));
+        }
+        Ok((climb / step) as usize)
+    }
+}
+
+const DIRECTIONS: [Position; 4] = [(1, 0), (0, -1), (-1, 0), (0, 1)];
+
+// Heights of the map, `None` for impassable cells like the '.' in the puzzle examples
+struct TrailMap {
+    width: i16,
+    height: i16,
+    heights: Vec<Option<u8>>,
+}
+
+impl TrailMap {
+    fn new(((width, height), data): &Puzzle) -> TrailMap {
+        TrailMap {
+            width: *width,
+            height: *height,
+            heights: data
+                .iter()
+                .map(|c| (*c as char).to_digit(10).map(|h| h as u8))
+                .collect(),
+        }
+    }
+
+    fn height_at(&self, (x, y): &Position) -> Option<u8> {
+        if *x < 0 || *y < 0 || *x >= self.width || *y >= self.height {
+            return None;
+        }
+        self.heights[(y * self.width + x) as usize]
+    }
+
+    fn next_positions<'a>(
+        &'a self,
+        position: &'a Position,
+        config: &'a TrailConfig,
+    ) -> impl Iterator<Item = Position> + 'a {
+        let next_height = self.height_at(position).unwrap() as i16 + config.step as i16;
+        DIRECTIONS
+            .iter()
+            .map(move |(dx, dy)| (position.0 + dx, position.1 + dy))
+            .filter(move |next| self.height_at(next).map(|h| h as i16) == Some(next_height))
+    }
+
+    fn trailheads(&self, config: &TrailConfig) -> Vec<Position> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|position| self.height_at(position) == Some(config.start_height))
+            .collect()
+    }
+
+    // Returns the score (reachable trail ends) and the rating (distinct trails) of a trailhead.
+    // Trails are counted layer by layer, every layer holds the number of trails reaching each
+    // position of the next height.
+    fn analyse_trailhead(
+        &self,
+        trailhead: Position,
+        config: &TrailConfig,
+    ) -> Result<(TrailCount, TrailCount), String> {
+        let mut layer: HashMap<Position, TrailCount> = HashMap::from([(trailhead, 1)]);
+        for _ in 0..config.length()? {
+            let mut next_layer: HashMap<Position, TrailCount> = HashMap::new();
+            for (position, trails) in layer {
+                for next_position in self.next_positions(&position, config) {
+                    let count = next_layer.entry(next_position).or_default();
+                    *count = count.checked_add(trails).ok_or("Trail count overflow")?;
+                }
+            }
+            layer = next_layer;
+        }
+
+        let rating = layer
+            .values()
+            .try_fold(0 as TrailCount, |acc, trails| acc.checked_add(*trails))
+            .ok_or("Trail count overflow")?;
+        Ok((layer.len() as TrailCount, rating))
     }
-    let mut unique_paths_sum = 0;
-    for &direction in directions {
-        let next_pos = (start_pos.0 + direction.0, start_pos.1 + direction.1);
-
-        if !is_out_of_bounds(&next_pos) && lookup_pos_value(&next_pos) == current_pos_value + 1 {
-            let unique_paths = explore_hiking_path(
-                next_pos,
-                &directions,
-                lookup_pos_value,
-                is_out_of_bounds,
-                reachable_ends,
-            );
-            unique_paths_sum += unique_paths
+
+    // Lists every distinct trail of a trailhead as sequence of positions
+    fn enumerate_trails(
+        &self,
+        trailhead: Position,
+        config: &TrailConfig,
+    ) -> Result<Vec<Vec<Position>>, String> {
+        let length = config.length()?;
+        let mut trails: Vec<Vec<Position>> = vec![];
+        let mut stack: Vec<Vec<Position>> = vec![vec![trailhead]];
+        while let Some(trail) = stack.pop() {
+            if trail.len() == length + 1 {
+                trails.push(trail);
+                continue;
+            }
+            for next_position in self.next_positions(trail.last().unwrap(), config) {
+                let mut next_trail = trail.clone();
+                next_trail.push(next_position);
+                stack.push(next_trail);
+            }
+        }
+        trails.sort();
+        Ok(trails)
+    }
+
+    // Renders the map with only the heights along the trail visible
+    fn render_trail(&self, trail: &[Position]) -> String {
+        let mut lines: Vec<String> = vec![];
+        for y in 0..self.height {
+            let line = (0..self.width)
+                .map(|x| match self.height_at(&(x, y)) {
+                    Some(h) if trail.contains(&(x, y)) => char::from_digit(h as u32, 10).unwrap(),
+                    _ => '.',
+                })
+                .collect::<String>();
+            lines.push(line);
         }
+        lines.join("\n")
     }
-    unique_paths_sum
 }
 
 fn parse_puzzle(input: &str) -> Puzzle {
@@ -101,4 +235,39 @@ mod tests {
         assert_eq!(solution_part_one.to_string(), "36");
         assert_eq!(solution_part_two.to_string(), "81");
     }
+
+    #[test]
+    fn test_trail_analysis() {
+        let input =
+            "89010123\n78121874\n87430965\n96549874\n45678903\n32019012\n01329801\n10456732";
+        let puzzle = parse_puzzle(input);
+        assert_eq!(solve_both_parts(&puzzle), (36, 81));
+
+        let input = ".....0.\n..4321.\n..5..2.\n..6543.\n..7..4.\n..8765.\n..9....";
+        let trail_map = TrailMap::new(&parse_puzzle(input));
+        let config = TrailConfig::default();
+        assert_eq!(trail_map.trailheads(&config), vec![(5, 0)]);
+        assert_eq!(trail_map.analyse_trailhead((5, 0), &config), Ok((1, 3)));
+
+        let trails = trail_map.enumerate_trails((5, 0), &config).unwrap();
+        assert_eq!(trails.len(), 3);
+        assert_eq!(
+            trail_map.render_trail(&trails[0]),
+            ".....0.\n..4321.\n..5....\n..6....\n..7....\n..8....\n..9...."
+        );
+
+        // Walking the same trails downhill
+        let config = TrailConfig {
+            start_height: 9,
+            end_height: 0,
+            step: -1,
+        };
+        assert_eq!(trail_map.analyse_trailhead((2, 6), &config), Ok((1, 3)));
+        let config = TrailConfig {
+            start_height: 0,
+            end_height: 9,
+            step: 2,
+        };
+        assert!(trail_map.analyse_trailhead((5, 0), &config).is_err());
+    }
 }
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 
 pub fn solve(input_file_path: &str) -> (String, String) {
     let input = std::fs::read_to_string(input_file_path).unwrap();
@@ -14,62 +15,231 @@ pub fn solve(input_file_path: &str) -> (String, String) {
     (solution_part_one, solution_part_two)
 }
 
-fn solve_part_one(input: &Vec<u64>) -> String {
-    let mut shortcuts: HashMap<(u64, u8), u64> = HashMap::new();
-    let solution: u64 = input.iter().map(|x| blink(*x, 25, &mut shortcuts)).sum();
-    solution.to_string()
+// Counts the stones of both parts without an upper limit and shows how few different numbers
+// there are after each blink
+pub fn explain(input_file_path: &str) -> Result<String, String> {
+    let input = std::fs::read_to_string(input_file_path).map_err(|e| e.to_string())?;
+    let input = input
+        .split_whitespace()
+        .map(|x| x.parse().map_err(|_| format!("Invalid stone {}", x)))
+        .collect::<Result<Vec<u64>, String>>()?;
+
+    let report = simulate::<BigCount>(&input, 75, &BlinkRules::default())?;
+    let mut lines: Vec<String> = vec![
+        format!(
+            "Stones after 25 blinks: {}",
+            count_stones::<BigCount>(&input, 25)
+        ),
+        format!("Stones after 75 blinks: {}", report.total),
+        "Different numbers after each blink:".to_string(),
+    ];
+    for (blink, distinct) in report.distinct_stones.iter().enumerate() {
+        lines.push(format!("{:>2}: {}", blink, distinct));
+    }
+    Ok(lines.join("\n"))
+}
+
+fn solve_part_one(input: &[u64]) -> String {
+    count_stones::<u64>(input, 25)
+}
+
+fn solve_part_two(input: &[u64]) -> String {
+    count_stones::<u64>(input, 75)
+}
+
+fn count_stones<C: StoneCount + Display>(input: &[u64], blinks: usize) -> String {
+    match simulate::<C>(input, blinks, &BlinkRules::default()) {
+        Ok(report) => report.total.to_string(),
+        Err(message) => message,
+    }
+}
+
+// Stones with the same number behave the same, so only the count per number is tracked
+type Stones<C> = HashMap<u64, C>;
+
+trait StoneCount: Clone {
+    fn one() -> Self;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
 }
 
-fn solve_part_two(input: &Vec<u64>) -> String {
-    let mut shortcuts: HashMap<(u64, u8), u64> = HashMap::new();
+impl StoneCount for u64 {
+    fn one() -> Self {
+        1
+    }
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        u64::checked_add(*self, *other)
+    }
+}
+
+impl StoneCount for u128 {
+    fn one() -> Self {
+        1
+    }
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        u128::checked_add(*self, *other)
+    }
+}
 
-    generate_shortcuts(&mut shortcuts, 75);
-    let solution: u64 = input
-        .iter()
-        .map(|x| {
-            return blink(*x, 75, &mut shortcuts);
-        })
-        .sum();
-    solution.to_string()
+// Unsigned integer of arbitrary size, little endian in base 2^32. Stones are only ever added
+// up, so that's all it can do besides printing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BigCount {
+    limbs: Vec<u32>,
 }
 
-fn blink(stone: u64, depth: u8, shortcuts: &mut HashMap<(u64, u8), u64>) -> u64 {
-    if depth == 0 {
-        return 1;
+impl StoneCount for BigCount {
+    fn one() -> Self {
+        BigCount { limbs: vec![1] }
     }
 
-    if stone < 10 {
-        if shortcuts.contains_key(&(stone, depth)) {
-            return shortcuts[&(stone, depth)];
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        let length = self.limbs.len().max(other.limbs.len());
+        let mut limbs: Vec<u32> = Vec::with_capacity(length + 1);
+        let mut carry = 0;
+        for i in 0..length {
+            let a = *self.limbs.get(i).unwrap_or(&0) as u64;
+            let b = *other.limbs.get(i).unwrap_or(&0) as u64;
+            let sum = a + b + carry;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
         }
+        if carry > 0 {
+            limbs.push(carry as u32);
+        }
+        Some(BigCount { limbs })
     }
+}
 
-    if stone == 0 {
-        return blink(1, depth - 1, shortcuts);
+impl Display for BigCount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // Repeatedly divide by 10^9 and collect the remainders as groups of nine digits
+        const CHUNK: u64 = 1_000_000_000;
+        let mut limbs = self.limbs.clone();
+        let mut chunks: Vec<u64> = vec![];
+        while limbs.iter().any(|limb| *limb != 0) {
+            let mut remainder = 0;
+            for limb in limbs.iter_mut().rev() {
+                let value = (remainder << 32) | *limb as u64;
+                *limb = (value / CHUNK) as u32;
+                remainder = value % CHUNK;
+            }
+            chunks.push(remainder);
+        }
+        match chunks.split_last() {
+            None => write!(f, "0"),
+            Some((first, rest)) => {
+                write!(f, "{}", first)?;
+                rest.iter()
+                    .rev()
+                    .try_for_each(|chunk| write!(f, "{:09}", chunk))
+            }
+        }
     }
+}
+
+// A stone of 0 becomes `zero_replacement`, a stone with an even number of digits in
+// `digit_base` splits into its two halves and any other stone is multiplied by `multiplier`
+#[derive(Debug, Clone, Copy)]
+struct BlinkRules {
+    zero_replacement: u64,
+    digit_base: u64,
+    split_even_digits: bool,
+    multiplier: u64,
+}
 
-    let stone_string = stone.to_string();
-    let has_even_digits = stone_string.len() % 2 == 0;
+impl Default for BlinkRules {
+    fn default() -> Self {
+        BlinkRules {
+            zero_replacement: 1,
+            digit_base: 10,
+            split_even_digits: true,
+            multiplier: 2024,
+        }
+    }
+}
 
-    if has_even_digits {
-        let (left, right) = stone_string.split_at(stone_string.len() / 2);
-        let left = left.parse::<u64>().unwrap();
-        let right = right.parse::<u64>().unwrap();
-        let left = blink(left, depth - 1, shortcuts);
-        let right = blink(right, depth - 1, shortcuts);
-        return left + right;
+impl BlinkRules {
+    fn transform(&self, stone: u64) -> Result<(u64, Option<u64>), String> {
+        // Digits can't be counted in base 0 or 1
+        if self.digit_base < 2 {
+            return Err(format!("Invalid digit base {}", self.digit_base));
+        }
+        if stone == 0 {
+            return Ok((self.zero_replacement, None));
+        }
+        if self.split_even_digits {
+            let mut digits = 0;
+            let mut rest = stone;
+            while rest > 0 {
+                rest /= self.digit_base;
+                digits += 1;
+            }
+            if digits % 2 == 0 {
+                let divisor = self.digit_base.pow(digits / 2);
+                return Ok((stone / divisor, Some(stone % divisor)));
+            }
+        }
+        match stone.checked_mul(self.multiplier) {
+            Some(product) => Ok((product, None)),
+            None => Err(format!("Stone {} overflows when multiplied", stone)),
+        }
     }
+}
 
-    blink(stone * 2024, depth - 1, shortcuts)
+#[derive(Debug)]
+struct BlinkReport<C> {
+    total: C,
+    // Number of different stone numbers, before the first and after every blink
+    distinct_stones: Vec<usize>,
 }
 
-fn generate_shortcuts(target: &mut HashMap<(u64, u8), u64>, depth: u8) {
-    for d in 1..depth + 1 {
-        for stone in 0..10 {
-            let solution = blink(stone, d, target);
-            target.insert((stone, d), solution);
+fn blink<C: StoneCount>(stones: &Stones<C>, rules: &BlinkRules) -> Result<Stones<C>, String> {
+    let mut next_stones: Stones<C> = HashMap::with_capacity(stones.len());
+    let mut add = |stone: u64, count: &C| -> Result<(), String> {
+        let entry = match next_stones.remove(&stone) {
+            Some(existing) => existing.checked_add(count).ok_or("Stone count overflow")?,
+            None => count.clone(),
+        };
+        next_stones.insert(stone, entry);
+        Ok(())
+    };
+    for (stone, count) in stones {
+        let (left, right) = rules.transform(*stone)?;
+        add(left, count)?;
+        if let Some(right) = right {
+            add(right, count)?;
         }
     }
+    Ok(next_stones)
+}
+
+fn simulate<C: StoneCount>(
+    initial: &[u64],
+    blinks: usize,
+    rules: &BlinkRules,
+) -> Result<BlinkReport<C>, String> {
+    let mut stones: Stones<C> = HashMap::new();
+    for stone in initial {
+        let count = match stones.remove(stone) {
+            Some(count) => count.checked_add(&C::one()).ok_or("Stone count overflow")?,
+            None => C::one(),
+        };
+        stones.insert(*stone, count);
+    }
+
+    let mut distinct_stones: Vec<usize> = vec![stones.len()];
+    for _ in 0..blinks {
+        stones = blink(&stones, rules)?;
+        distinct_stones.push(stones.len());
+    }
+
+    let mut counts = stones.into_values();
+    let first = counts.next().ok_or("No stones left")?;
+    let total = counts.try_fold(first, |total, count| total.checked_add(&count));
+    Ok(BlinkReport {
+        total: total.ok_or("Stone count overflow")?,
+        distinct_stones,
+    })
 }
 
 #[cfg(test)]
@@ -88,4 +258,64 @@ mod tests {
         let solution = solve_part_one(&input);
         assert_eq!(solution, "55312");
     }
+
+    #[test]
+    fn test_frequency_engine() {
+        let input = vec![125, 17];
+        let rules = BlinkRules::default();
+        let report = simulate::<u64>(&input, 6, &rules).unwrap();
+        assert_eq!(report.total, 22);
+        assert_eq!(report.distinct_stones[..4], [2, 3, 4, 5]);
+        assert_eq!(solve_part_one(&input), "55312");
+        assert_eq!(solve_part_two(&input), "65601038650482");
+
+        // u64 runs out after a few hundred blinks, the big count keeps going
+        assert_eq!(count_stones::<u64>(&input, 1000), "Stone count overflow");
+        assert_eq!(
+            count_stones::<BigCount>(&input, 75),
+            count_stones::<u128>(&input, 75)
+        );
+        let big = simulate::<BigCount>(&input, 1000, &rules).unwrap();
+        assert!(big.total.to_string().len() > 150);
+        assert!(big.distinct_stones.iter().all(|distinct| *distinct < 5000));
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        // Without splitting the numbers grow until they overflow
+        let rules = BlinkRules {
+            split_even_digits: false,
+            multiplier: 3,
+            ..BlinkRules::default()
+        };
+        let report = simulate::<u64>(&[0], 40, &rules).unwrap();
+        assert_eq!(report.total, 1);
+        assert!(simulate::<u64>(&[0], 45, &rules).is_err());
+
+        // 6 is 110 in binary and is multiplied to 12 = 1100, which splits into 11 and 00
+        let rules = BlinkRules {
+            digit_base: 2,
+            multiplier: 2,
+            ..BlinkRules::default()
+        };
+        let report = simulate::<u64>(&[6, 6], 2, &rules).unwrap();
+        assert_eq!(report.total, 4);
+        assert_eq!(report.distinct_stones, vec![1, 1, 2]);
+
+        for digit_base in [0, 1] {
+            let rules = BlinkRules {
+                digit_base,
+                ..BlinkRules::default()
+            };
+            let error = simulate::<u64>(&[6], 1, &rules).unwrap_err();
+            assert_eq!(error, format!("Invalid digit base {}", digit_base));
+        }
+
+        let count = BigCount {
+            limbs: vec![u32::MAX, u32::MAX],
+        };
+        let count = count.checked_add(&BigCount::one()).unwrap();
+        assert_eq!(count.to_string(), (u64::MAX as u128 + 1).to_string());
+        assert_eq!(BigCount { limbs: vec![0] }.to_string(), "0");
+    }
 }
@@ -1,4 +1,5 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashSet, VecDeque};
+use std::path::Path;
 
 type Dimension = (usize, usize);
 type Position = (i32, i32);
@@ -11,6 +12,36 @@ pub fn solve(input_file_path: &str) -> (String, String) {
     (solution_part_one, solution_part_two)
 }
 
+// Lists the measures of every region and draws the regions with their fences
+pub fn explain(input_file_path: &str) -> Result<String, String> {
+    let input = std::fs::read_to_string(input_file_path).map_err(|e| e.to_string())?;
+    let puzzle = parse_puzzle(&input);
+    let regions = find_regions(&puzzle);
+
+    let mut lines: Vec<String> = vec![];
+    for region in &regions {
+        let ((left, top), (right, bottom)) = region.bounding_box;
+        lines.push(format!(
+            "{} at ({}, {})..=({}, {}): area {}, perimeter {}, sides {}, holes {}",
+            region.plant as char,
+            left,
+            top,
+            right,
+            bottom,
+            region.area,
+            region.perimeter,
+            region.sides,
+            region.holes
+        ));
+    }
+
+    let image_path = Path::new(input_file_path).with_file_name("regions.ppm");
+    std::fs::write(&image_path, export_regions_ppm(&puzzle, &regions, 4))
+        .map_err(|e| e.to_string())?;
+    lines.push(format!("Regions written to {}", image_path.display()));
+    Ok(lines.join("\n"))
+}
+
 fn parse_puzzle(input: &str) -> Puzzle {
     let width = input.lines().next().unwrap().len();
     let height = input.lines().count();
@@ -22,119 +53,184 @@ fn parse_puzzle(input: &str) -> Puzzle {
     ((width, height), data)
 }
 
-fn solve_both_parts(((width, height), data): &Puzzle) -> (String, String) {
+fn solve_both_parts(puzzle: &Puzzle) -> (String, String) {
+    let regions = find_regions(puzzle);
+    let price: usize = regions.iter().map(|r| r.area * r.perimeter).sum();
+    let price_discount: usize = regions.iter().map(|r| r.area * r.sides).sum();
+
+    (price.to_string(), price_discount.to_string())
+}
+
+#[derive(Debug, Clone)]
+struct Region {
+    plant: u8,
+    cells: Vec<Position>,
+    area: usize,
+    perimeter: usize,
+    sides: usize,
+    // Top left and bottom right cell (inclusive)
+    bounding_box: (Position, Position),
+    holes: usize,
+}
+
+const DIRECTIONS: [Position; 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+// Labels all connected regions of equal plants with a flood fill
+fn find_regions(((width, height), data): &Puzzle) -> Vec<Region> {
     let w = *width as i32;
     let h = *height as i32;
+    let is_inside = |(x, y): &Position| *x >= 0 && *y >= 0 && *x < w && *y < h;
 
-    let mut visited: Vec<bool> = vec![false; (w * h) as usize];
+    let mut visited: Vec<bool> = vec![false; width * height];
+    let mut regions: Vec<Region> = vec![];
 
-    let mut price = 0;
-    let mut price_discount = 0;
-
-    for y in 0..*width {
-        for x in 0..*height {
-            if visited[y * width + x] {
+    for y in 0..h {
+        for x in 0..w {
+            if visited[(y * w + x) as usize] {
                 continue;
             }
 
-            let mut neighbors: VecDeque<Position> = VecDeque::new();
-            neighbors.push_back((x as i32, y as i32));
-
-            let plant_type = data[y * width + x];
-            let mut area = 0;
+            let plant = data[(y * w + x) as usize];
+            let mut neighbors: VecDeque<Position> = VecDeque::from([(x, y)]);
+            visited[(y * w + x) as usize] = true;
+            let mut cells: Vec<Position> = Vec::new();
             let mut perimeter = 0;
-            let mut plants_in_area: Vec<Position> = Vec::new();
-
-            while !neighbors.is_empty() {
-                let (node_x, node_y) = neighbors.pop_front().unwrap();
-
-                if visited[(node_y * w + node_x) as usize] {
-                    continue;
-                }
-
-                visited[(node_y * w + node_x) as usize] = true;
-                plants_in_area.push((node_x, node_y));
-                area += 1;
-                perimeter += 4;
-
-                let possible_neighbors = vec![
-                    (node_x, node_y - 1),
-                    (node_x + 1, node_y),
-                    (node_x, node_y + 1),
-                    (node_x - 1, node_y),
-                ];
-
-                for (n_x, n_y) in possible_neighbors {
-                    if n_x >= w || n_x < 0 || n_y < 0 || n_y >= h {
-                        continue;
-                    }
-
-                    let pos_as_index = (n_y * w + n_x) as usize;
-                    let is_same_plant = data[pos_as_index] == plant_type;
-                    let is_visited = visited[pos_as_index];
-
-                    if is_same_plant {
-                        perimeter -= 1;
 
-                        if !is_visited {
-                            neighbors.push_back((n_x, n_y));
-                        }
+            while let Some((node_x, node_y)) = neighbors.pop_front() {
+                cells.push((node_x, node_y));
+                for (dx, dy) in DIRECTIONS {
+                    let neighbor = (node_x + dx, node_y + dy);
+                    let pos_as_index = (neighbor.1 * w + neighbor.0) as usize;
+                    if !is_inside(&neighbor) || data[pos_as_index] != plant {
+                        perimeter += 1;
+                    } else if !visited[pos_as_index] {
+                        visited[pos_as_index] = true;
+                        neighbors.push_back(neighbor);
                     }
                 }
             }
-            let unique_edges = count_unique_edges(plants_in_area);
-            price += area * perimeter;
-            price_discount += area * unique_edges;
+
+            cells.sort_by_key(|&(x, y)| (y, x));
+            let cell_set: HashSet<Position> = cells.iter().copied().collect();
+            let min_x = cells.iter().map(|c| c.0).min().unwrap();
+            let max_x = cells.iter().map(|c| c.0).max().unwrap();
+            let min_y = cells.iter().map(|c| c.1).min().unwrap();
+            let max_y = cells.iter().map(|c| c.1).max().unwrap();
+            let bounding_box = ((min_x, min_y), (max_x, max_y));
+
+            regions.push(Region {
+                plant,
+                area: cells.len(),
+                perimeter,
+                sides: count_corners(&cell_set),
+                holes: count_holes(&cell_set, bounding_box),
+                bounding_box,
+                cells,
+            });
         }
     }
-
-    (price.to_string(), price_discount.to_string())
+    regions
 }
 
-fn count_unique_edges(area: Vec<Position>) -> i32 {
-    let directions = vec![(0, -1), (1, 0), (0, 1), (-1, 0)];
-    let mut unique_edges = 0;
-    for (dx, dy) in directions {
-        let edges_in_direction = area
-            .iter()
-            .filter(|(x, y)| !area.contains(&(x + dx, y + dy)))
-            .collect::<Vec<&Position>>();
-
-        let group_horizontal = dx == 0;
-
-        let mut grouped_by_merge_axis: HashMap<i32, Vec<i32>> = HashMap::new();
-        for (e_x, e_y) in edges_in_direction {
-            let key = if group_horizontal { *e_y } else { *e_x };
-            let value = if group_horizontal { e_x } else { e_y };
-
-            let entry = grouped_by_merge_axis.entry(key).or_insert(Vec::new());
-            entry.push(*value);
+// A polygon has as many sides as corners. For every cell and diagonal direction there is an
+// outer corner if both orthogonal neighbours are outside the region and an inner corner if
+// both are inside, but the diagonal neighbour isn't.
+fn count_corners(cells: &HashSet<Position>) -> usize {
+    let mut corners = 0;
+    for (x, y) in cells {
+        for (dx, dy) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+            let horizontal = cells.contains(&(x + dx, *y));
+            let vertical = cells.contains(&(*x, y + dy));
+            let diagonal = cells.contains(&(x + dx, y + dy));
+            if (!horizontal && !vertical) || (horizontal && vertical && !diagonal) {
+                corners += 1;
+            }
         }
+    }
+    corners
+}
 
-        let mut unique_edges_in_direction = 0;
-        for sweep in grouped_by_merge_axis.values() {
-            let sweep_copy = sweep.clone();
-            unique_edges_in_direction += count_intervals(sweep_copy);
+// Counts the pockets of foreign cells enclosed by the region. Everything reachable from outside
+// the bounding box isn't a hole. Foreign cells are connected diagonally as well, otherwise a
+// pocket could leak out between two region cells only touching at their corners.
+fn count_holes(
+    cells: &HashSet<Position>,
+    ((min_x, min_y), (max_x, max_y)): (Position, Position),
+) -> usize {
+    let (min_x, min_y, max_x, max_y) = (min_x - 1, min_y - 1, max_x + 1, max_y + 1);
+    let mut visited: HashSet<Position> = HashSet::new();
+    let mut components = 0;
+
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            if cells.contains(&(x, y)) || visited.contains(&(x, y)) {
+                continue;
+            }
+            components += 1;
+            let mut queue: VecDeque<Position> = VecDeque::from([(x, y)]);
+            visited.insert((x, y));
+            while let Some((cx, cy)) = queue.pop_front() {
+                for dx in -1..=1 {
+                    for dy in -1..=1 {
+                        let next = (cx + dx, cy + dy);
+                        let in_box = next.0 >= min_x
+                            && next.0 <= max_x
+                            && next.1 >= min_y
+                            && next.1 <= max_y;
+                        if in_box && !cells.contains(&next) && visited.insert(next) {
+                            queue.push_back(next);
+                        }
+                    }
+                }
+            }
         }
-        unique_edges += unique_edges_in_direction;
     }
-
-    unique_edges
+    // The frame around the bounding box is one component that's always outside
+    components - 1
 }
 
-fn count_intervals(mut input: Vec<i32>) -> i32 {
-    input.sort();
-    if input.len() <= 1 {
-        input.len() as i32
-    } else {
-        let mut intervals = 1;
-        for i in 0..input.len() - 1 {
-            if (input[i + 1] - input[i]).abs() != 1 {
-                intervals += 1;
+// Renders the regions as binary PPM image with `scale` pixels per plot. Each region gets its
+// own colour and fences are drawn in black, so sides can be counted by eye.
+fn export_regions_ppm(((width, height), _): &Puzzle, regions: &[Region], scale: usize) -> Vec<u8> {
+    let (image_width, image_height) = (width * scale, height * scale);
+    let mut pixels: Vec<[u8; 3]> = vec![[0, 0, 0]; image_width * image_height];
+
+    for (index, region) in regions.iter().enumerate() {
+        // Spread hues with the golden ratio, so neighbouring regions rarely look alike
+        let hue = (index as f64 * 0.618_033_988_75).fract() * 6.0;
+        let rising = (hue.fract() * 200.0) as u8 + 55;
+        let falling = 255 - rising + 55;
+        let colour = match hue as u8 {
+            0 => [255, rising, 55],
+            1 => [falling, 255, 55],
+            2 => [55, 255, rising],
+            3 => [55, falling, 255],
+            4 => [rising, 55, 255],
+            _ => [255, 55, falling],
+        };
+
+        let cell_set: HashSet<&Position> = region.cells.iter().collect();
+        for (x, y) in &region.cells {
+            let fence_top = !cell_set.contains(&(*x, y - 1));
+            let fence_bottom = !cell_set.contains(&(*x, y + 1));
+            let fence_left = !cell_set.contains(&(x - 1, *y));
+            let fence_right = !cell_set.contains(&(x + 1, *y));
+            for py in 0..scale {
+                for px in 0..scale {
+                    let is_fence = (fence_top && py == 0)
+                        || (fence_bottom && py == scale - 1)
+                        || (fence_left && px == 0)
+                        || (fence_right && px == scale - 1);
+                    let pixel = (*y as usize * scale + py) * image_width + *x as usize * scale + px;
+                    pixels[pixel] = if is_fence { [0, 0, 0] } else { colour };
+                }
             }
         }
-        intervals
     }
+
+    let mut image = format!("P6\n{} {}\n255\n", image_width, image_height).into_bytes();
+    image.extend(pixels.into_iter().flatten());
+    image
 }
 
 #[cfg(test)]
@@ -158,15 +254,6 @@ mod tests {
         assert_eq!(solution_two, "1206");
     }
 
-    #[test]
-    fn test_interval_counter() {
-        let output = count_intervals(vec![1, 2, 3]);
-        assert_eq!(output, 1);
-
-        let output = count_intervals(vec![1, 3]);
-        assert_eq!(output, 2);
-    }
-
     #[test]
     #[rustfmt::skip]
     fn test_edge_detector() {
@@ -175,14 +262,14 @@ mod tests {
         let input: Vec<Position> = vec![
             (0, 0)
         ];
-        let solution = count_unique_edges(input);
+        let solution = count_corners(&input.into_iter().collect());
         assert_eq!(solution, 4);
 
         // Check field strip
         let input: Vec<Position> = vec![
             (0, 0), (1, 0), (2, 0)
         ];
-        let solution = count_unique_edges(input);
+        let solution = count_corners(&input.into_iter().collect());
         assert_eq!(solution, 4);
 
         // Check rectangular field
@@ -191,7 +278,7 @@ mod tests {
             (0, 1),(1, 1),(2, 1),
             (0, 2),(1, 2),(2, 2),
         ];
-        let solution = count_unique_edges(input);
+        let solution = count_corners(&input.into_iter().collect());
         assert_eq!(solution, 4);
 
         // Check right L field
@@ -200,7 +287,7 @@ mod tests {
                             (2, 1),
                             (2, 2)
         ];
-        let solution = count_unique_edges(input);
+        let solution = count_corners(&input.into_iter().collect());
         assert_eq!(solution, 6);
 
         // Check left L field
@@ -209,7 +296,7 @@ mod tests {
             (0, 1),
             (0, 2)
         ];
-        let solution = count_unique_edges(input);
+        let solution = count_corners(&input.into_iter().collect());
         assert_eq!(solution, 6);
 
         // Check complex shape
@@ -220,7 +307,7 @@ mod tests {
             (0,3),
             (0,4),(1,4),(2,4),(3,4),
         ];
-        let solution = count_unique_edges(input);
+        let solution = count_corners(&input.into_iter().collect());
         assert_eq!(solution, 12);
 
         // Check field with holes
@@ -229,7 +316,36 @@ mod tests {
             (0, 1),       (2, 1),
             (0, 2),(1, 2),(2, 2),
         ];
-        let solution = count_unique_edges(input);
+        let solution = count_corners(&input.into_iter().collect());
         assert_eq!(solution, 8);
     }
+
+    #[test]
+    fn test_regions() {
+        let input = "AAAAAA\nAAABBA\nAAABBA\nABBAAA\nABBAAA\nAAAAAA";
+        let puzzle = parse_puzzle(input);
+        let (_, solution_two) = solve_both_parts(&puzzle);
+        assert_eq!(solution_two, "368");
+
+        let regions = find_regions(&puzzle);
+        assert_eq!(regions.len(), 3);
+        let a = &regions[0];
+        assert_eq!((a.plant, a.area, a.perimeter, a.sides), (b'A', 28, 40, 12));
+        assert_eq!(a.bounding_box, ((0, 0), (5, 5)));
+        assert_eq!(a.holes, 1);
+        let b = &regions[1];
+        assert_eq!((b.plant, b.area, b.sides, b.holes), (b'B', 4, 4, 0));
+        assert_eq!(b.cells, vec![(3, 1), (4, 1), (3, 2), (4, 2)]);
+
+        let input = "OOOOO\nOXOXO\nOOOOO\nOXOXO\nOOOOO";
+        let puzzle = parse_puzzle(input);
+        let regions = find_regions(&puzzle);
+        assert_eq!(regions.len(), 5);
+        assert_eq!((regions[0].sides, regions[0].holes), (20, 4));
+
+        let image = export_regions_ppm(&puzzle, &regions, 4);
+        let header = b"P6\n20 20\n255\n";
+        assert!(image.starts_with(header));
+        assert_eq!(image.len(), header.len() + 20 * 20 * 3);
+    }
 }
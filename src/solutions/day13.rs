@@ -9,50 +9,49 @@ pub fn solve(input_file_path: &str) -> (String, String) {
 
 #[derive(Debug)]
 struct Claw {
-    price_position: (i64, i64),
-    button_a: (i64, i64),
-    button_b: (i64, i64),
+    price_position: (i128, i128),
+    button_a: (i128, i128),
+    button_b: (i128, i128),
 }
 
-fn solve_part_one(claws: &Vec<Claw>) -> String {
-    let mut tokens = 0;
-    for claw in claws {
-        match solve_claw(&claw) {
-            Ok((button_1, button_2)) => {
-                if button_1 <= 100 && button_2 <= 100 {
-                    tokens += 3 * button_1 + button_2;
-                }
-            }
-            Err(_) => {}
-        }
-    }
-    tokens.to_string()
+const TOKENS_BUTTON_A: i128 = 3;
+const TOKENS_BUTTON_B: i128 = 1;
+
+fn solve_part_one(claws: &[Claw]) -> String {
+    count_tokens(claws, 0, Some(100))
+}
+
+fn solve_part_two(claws: &[Claw]) -> String {
+    count_tokens(claws, 10000000000000, None)
 }
 
-fn solve_part_two(claws: &Vec<Claw>) -> String {
-    let mut tokens = 0;
+fn count_tokens(claws: &[Claw], offset: i128, max_presses: Option<i128>) -> String {
+    let mut tokens: i128 = 0;
     for claw in claws {
-        let offset: i64 = 10000000000000;
-        let pos = (
-            claw.price_position.0 + offset,
-            claw.price_position.1 + offset,
-        );
         let claw_update: Claw = Claw {
-            price_position: pos,
+            price_position: (
+                claw.price_position.0 + offset,
+                claw.price_position.1 + offset,
+            ),
             button_a: claw.button_a,
             button_b: claw.button_b,
         };
-        match solve_claw(&claw_update) {
-            Ok((button_1, button_2)) => {
-                tokens += 3 * button_1 + button_2;
-            }
-            Err(_) => {}
+        if let Ok((button_1, button_2)) = solve_claw(&claw_update, max_presses) {
+            tokens += TOKENS_BUTTON_A * button_1 + TOKENS_BUTTON_B * button_2;
         }
     }
     tokens.to_string()
 }
 
-fn solve_claw(claw: &Claw) -> Result<(i64, i64), String> {
+fn checked_mul(a: i128, b: i128) -> Result<i128, String> {
+    a.checked_mul(b).ok_or("Overflow".to_string())
+}
+
+fn checked_sub(a: i128, b: i128) -> Result<i128, String> {
+    a.checked_sub(b).ok_or("Overflow".to_string())
+}
+
+fn solve_claw(claw: &Claw, max_presses: Option<i128>) -> Result<(i128, i128), String> {
     /*  Idea - Calculate button_click# by inverting a 2x2 matrix (**sad numeric noises**)
 
        Price := (p_x, p_y)^T
@@ -65,49 +64,178 @@ fn solve_claw(claw: &Claw) -> Result<(i64, i64), String> {
 
        Clicks = (Claw_1 Claw_2)^-1 * Clicks
 
+       If both buttons move the claw along the same line the matrix has no inverse, but
+       there may still be many solutions. See `solve_collinear_claw`.
     */
 
     let (a_x, a_y) = claw.button_a;
     let (b_x, b_y) = claw.button_b;
     let (p_x, p_y) = claw.price_position;
 
-    let det = a_x * b_y - b_x * a_y;
+    let det = checked_sub(checked_mul(a_x, b_y)?, checked_mul(b_x, a_y)?)?;
 
     if det == 0 {
-        return Err("Not solvable".to_string());
+        return solve_collinear_claw(claw, max_presses);
     }
 
     // "Inverse" of A (not scaled by det)
     let (a, b, c, d) = (b_y, -b_x, -a_y, a_x);
     // Calculate unscaled solution
-    let (c_1, c_2) = (a * p_x + b * p_y, c * p_x + d * p_y);
+    let c_1 = checked_mul(a, p_x)?
+        .checked_add(checked_mul(b, p_y)?)
+        .ok_or("Overflow")?;
+    let c_2 = checked_mul(c, p_x)?
+        .checked_add(checked_mul(d, p_y)?)
+        .ok_or("Overflow")?;
     // Check if scaled solution is an integer
-    if c_1 % det == 0 && c_2 % det == 0 {
-        Ok((c_1 / det, c_2 / det))
+    if c_1 % det != 0 || c_2 % det != 0 {
+        return Err("Not solvable with int".to_string());
+    }
+    let (c_1, c_2) = (c_1 / det, c_2 / det);
+    let max_presses = max_presses.unwrap_or(i128::MAX);
+    if c_1 < 0 || c_2 < 0 || c_1 > max_presses || c_2 > max_presses {
+        return Err("Not solvable within press limits".to_string());
+    }
+    Ok((c_1, c_2))
+}
+
+// Both buttons and the price lie on one line through the origin, which reduces the claw to
+// the diophantine equation a * n + b * m = p along that line. All solutions are
+// n = n_0 + k * b / g, m = m_0 - k * a / g and since the token costs are linear in k, the
+// cheapest one is at one end of the valid range of k.
+fn solve_collinear_claw(claw: &Claw, max_presses: Option<i128>) -> Result<(i128, i128), String> {
+    let (a_x, a_y) = claw.button_a;
+    let (b_x, b_y) = claw.button_b;
+    let (p_x, p_y) = claw.price_position;
+
+    let on_line = |(v_x, v_y): (i128, i128)| -> Result<bool, String> {
+        Ok(checked_mul(v_x, p_y)? == checked_mul(v_y, p_x)?)
+    };
+    if !on_line(claw.button_a)? || !on_line(claw.button_b)? {
+        return Err("Not solvable".to_string());
+    }
+
+    let (a, b, p) = if a_x != 0 || b_x != 0 {
+        (a_x, b_x, p_x)
     } else {
-        Err("Not solvable with int".to_string())
+        (a_y, b_y, p_y)
+    };
+    if a == 0 && b == 0 {
+        return if p_x == 0 && p_y == 0 {
+            Ok((0, 0))
+        } else {
+            Err("Not solvable".to_string())
+        };
+    }
+
+    let (g, x_0, y_0) = extended_gcd(a, b);
+    if p % g != 0 {
+        return Err("Not solvable with int".to_string());
     }
+    let (n_0, m_0) = (checked_mul(x_0, p / g)?, checked_mul(y_0, p / g)?);
+    let (n_step, m_step) = (b / g, -a / g);
+
+    // Range of k with 0 <= n_0 + k * n_step <= max and 0 <= m_0 + k * m_step <= max
+    let (mut k_min, mut k_max) = (i128::MIN, i128::MAX);
+    for (base, step) in [(n_0, n_step), (m_0, m_step)] {
+        if step == 0 {
+            if base < 0 || max_presses.is_some_and(|max| base > max) {
+                return Err("Not solvable within press limits".to_string());
+            }
+            continue;
+        }
+        let lower = div_ceil(-base, step.abs());
+        let upper = match max_presses {
+            Some(max) => div_floor(checked_sub(max, base)?, step.abs()),
+            None => i128::MAX,
+        };
+        let (lower, upper) = if step > 0 {
+            (lower, upper)
+        } else {
+            (-upper, -lower)
+        };
+        k_min = k_min.max(lower);
+        k_max = k_max.min(upper);
+    }
+    if k_min > k_max {
+        return Err("Not solvable within press limits".to_string());
+    }
+
+    let presses_for = |k: i128| -> Result<(i128, i128), String> {
+        Ok((
+            n_0.checked_add(checked_mul(k, n_step)?).ok_or("Overflow")?,
+            m_0.checked_add(checked_mul(k, m_step)?).ok_or("Overflow")?,
+        ))
+    };
+    let tokens_for = |(n, m): (i128, i128)| -> Result<i128, String> {
+        checked_mul(TOKENS_BUTTON_A, n)?
+            .checked_add(checked_mul(TOKENS_BUTTON_B, m)?)
+            .ok_or("Overflow".to_string())
+    };
+    // Without a press limit one end of the range is unbounded, but the costs always rise
+    // towards it, so only the end the slope points away from is evaluated
+    let slope = checked_mul(TOKENS_BUTTON_A, n_step)?
+        .checked_add(checked_mul(TOKENS_BUTTON_B, m_step)?)
+        .ok_or("Overflow")?;
+    let k = if slope >= 0 { k_min } else { k_max };
+    if k == i128::MIN || k == i128::MAX {
+        return Err("Overflow".to_string());
+    }
+    let presses = presses_for(k)?;
+    // The caller adds up the tokens, so they have to fit as well
+    tokens_for(presses).map(|_| presses)
+}
+
+// Returns (g, x, y) with a * x + b * y = g and g > 0
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_s, mut s) = (1, 0);
+    let (mut old_t, mut t) = (0, 1);
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_s, s) = (s, old_s - q * s);
+        (old_t, t) = (t, old_t - q * t);
+    }
+    if old_r < 0 {
+        (-old_r, -old_s, -old_t)
+    } else {
+        (old_r, old_s, old_t)
+    }
+}
+
+fn div_floor(a: i128, b: i128) -> i128 {
+    let q = a / b;
+    if a % b != 0 && (a < 0) != (b < 0) {
+        q - 1
+    } else {
+        q
+    }
+}
+
+fn div_ceil(a: i128, b: i128) -> i128 {
+    -div_floor(-a, b)
 }
 
 fn parse_puzzle(input: &str) -> Vec<Claw> {
     let mut claws: Vec<Claw> = Vec::new();
     let mut lines = input.lines();
 
-    let parse_button = |line: &str| -> (i64, i64) {
+    let parse_button = |line: &str| -> (i128, i128) {
         let split = line.split_whitespace().collect::<Vec<&str>>();
         let x = split[2].replace("X+", "").replace(",", "");
         let y = split[3].replace("Y+", "");
-        let x = x.parse::<i32>().unwrap() as i64;
-        let y = y.parse::<i32>().unwrap() as i64;
+        let x = x.parse::<i128>().unwrap();
+        let y = y.parse::<i128>().unwrap();
         (x, y)
     };
 
-    let parse_prize = |line: &str| -> (i64, i64) {
+    let parse_prize = |line: &str| -> (i128, i128) {
         let split = line.split_whitespace().collect::<Vec<&str>>();
         let x = split[1].replace("X=", "").replace(",", "");
         let y = split[2].replace("Y=", "");
-        let x = x.parse::<i32>().unwrap() as i64;
-        let y = y.parse::<i32>().unwrap() as i64;
+        let x = x.parse::<i128>().unwrap();
+        let y = y.parse::<i128>().unwrap();
         (x, y)
     };
 
@@ -146,4 +274,62 @@ mod tests {
         let solution = solve_part_two(&claws);
         assert_eq!(solution, "875318608908");
     }
+
+    fn claw(button_a: (i128, i128), button_b: (i128, i128), price: (i128, i128)) -> Claw {
+        Claw {
+            price_position: price,
+            button_a,
+            button_b,
+        }
+    }
+
+    #[test]
+    fn test_collinear_claw() {
+        // B is cheaper per distance: 10 * B
+        let c = claw((2, 4), (1, 2), (10, 20));
+        assert_eq!(solve_claw(&c, None), Ok((0, 10)));
+
+        // A is cheaper per distance, but 21 is odd so one B press is required
+        let c = claw((10, 10), (1, 1), (21, 21));
+        assert_eq!(solve_claw(&c, None), Ok((2, 1)));
+
+        // Press limit forces more A presses
+        let c = claw((2, 2), (1, 1), (10, 10));
+        assert_eq!(solve_claw(&c, Some(4)), Ok((3, 4)));
+
+        // Button B doesn't move along x
+        let c = claw((0, 3), (0, 5), (0, 7));
+        assert!(solve_claw(&c, None).is_err());
+        let c = claw((0, 3), (0, 5), (0, 11));
+        assert_eq!(solve_claw(&c, None), Ok((2, 1)));
+
+        // Price not on the line
+        let c = claw((2, 4), (1, 2), (10, 21));
+        assert!(solve_claw(&c, None).is_err());
+
+        // No integer solution
+        let c = claw((4, 4), (6, 6), (9, 9));
+        assert!(solve_claw(&c, None).is_err());
+
+        // Without a press limit one end of the range is unbounded
+        let c = claw((0, 0), (1, 1), (5, 5));
+        assert_eq!(solve_claw(&c, None), Ok((0, 5)));
+        let c = claw((3, 3), (-1, -1), (4, 4));
+        assert_eq!(solve_claw(&c, None), Ok((2, 2)));
+        let c = claw((-1, -2), (3, 6), (4, 8));
+        assert_eq!(solve_claw(&c, None), Ok((2, 2)));
+    }
+
+    #[test]
+    fn test_large_offsets() {
+        let c = claw((94, 34), (22, 67), (8400, 5400));
+        assert_eq!(solve_claw(&c, Some(100)), Ok((80, 40)));
+
+        let (n, m): (i128, i128) = (10_i128.pow(27), 3 * 10_i128.pow(27) + 5);
+        let c = claw((26, 66), (67, 21), (26 * n + 67 * m, 66 * n + 21 * m));
+        assert_eq!(solve_claw(&c, None), Ok((n, m)));
+
+        let c = claw((26, 66), (67, 21), (i128::MAX, i128::MAX));
+        assert_eq!(solve_claw(&c, None), Err("Overflow".to_string()));
+    }
 }
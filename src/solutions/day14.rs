@@ -1,5 +1,4 @@
-use std::cmp::max;
-use std::collections::{HashSet, VecDeque};
+use std::collections::HashSet;
 
 type Puzzle = Vec<Robot>;
 type Position = (i32, i32);
@@ -15,6 +14,33 @@ pub fn solve(input_file_path: &str) -> (String, String) {
     (solution_part_one, solution_part_two)
 }
 
+// Compares the steps both spread metrics pick for the picture and draws the robots at the step
+// of part two
+pub fn explain(input_file_path: &str) -> Result<String, String> {
+    let input = std::fs::read_to_string(input_file_path).map_err(|e| e.to_string())?;
+    let puzzle = parse_puzzle(&input);
+    let (width, height) = (101, 103);
+
+    let mut lines: Vec<String> = vec![];
+    let mut metric_steps: Vec<(SpreadMetric, i64)> = vec![];
+    for metric in [SpreadMetric::Variance, SpreadMetric::Entropy] {
+        let steps = find_easter_egg(width, height, &puzzle, metric)?;
+        lines.push(format!("{:?}: picture after {} steps", metric, steps));
+        metric_steps.push((metric, steps));
+    }
+    // Part two answers with the variance
+    let (_, steps) = metric_steps
+        .into_iter()
+        .find(|(metric, _)| *metric == SpreadMetric::Variance)
+        .unwrap();
+    let robot_positions = puzzle
+        .iter()
+        .map(|robot| simulate_robot(robot, width, height, steps as i32))
+        .collect::<HashSet<Position>>();
+    lines.push(visualize(&robot_positions, width, height));
+    Ok(lines.join("\n"))
+}
+
 fn solve_part_one(width: i32, height: i32, puzzle: &Puzzle) -> String {
     let middle = (width / 2, height / 2);
     let simulations_steps = 100;
@@ -38,26 +64,113 @@ fn solve_part_one(width: i32, height: i32, puzzle: &Puzzle) -> String {
 }
 
 fn solve_part_two(width: i32, height: i32, puzzle: &Puzzle) -> String {
-    let cluster_size_threshold = 115; // Found after pressing enter multiple thousand times
+    match find_easter_egg(width, height, puzzle, SpreadMetric::Variance) {
+        Ok(steps) => steps.to_string(),
+        Err(message) => message,
+    }
+}
 
-    for steps in 0..20000 {
-        let robot_positions: Vec<Position> = puzzle
-            .iter()
-            .map(|robot| simulate_robot(robot, width, height, steps))
-            .collect();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SpreadMetric {
+    Variance,
+    // Shannon entropy of the coordinate histogram, doesn't care where the robots cluster
+    Entropy,
+}
+
+// The x coordinates repeat every `width` steps and the y coordinates every `height` steps. The
+// picture is the frame in which the robots are most clustered, so each axis is ordered best at
+// one step within its period. Both steps are combined with the chinese remainder theorem.
+fn find_easter_egg(
+    width: i32,
+    height: i32,
+    puzzle: &Puzzle,
+    metric: SpreadMetric,
+) -> Result<i64, String> {
+    if puzzle.is_empty() {
+        return Err("No robots to form a picture".to_string());
+    }
+    let x_axis: Vec<(i32, i32)> = puzzle.iter().map(|((x, _), (vx, _))| (*x, *vx)).collect();
+    let y_axis: Vec<(i32, i32)> = puzzle.iter().map(|((_, y), (_, vy))| (*y, *vy)).collect();
+    let step_x = find_most_ordered_step(&x_axis, width, metric);
+    let step_y = find_most_ordered_step(&y_axis, height, metric);
 
-        let robot_positions: HashSet<Position> =
-            HashSet::from_iter(robot_positions.iter().cloned());
+    chinese_remainder(
+        (step_x as i64, width as i64),
+        (step_y as i64, height as i64),
+    )
+    .map(|(steps, _)| steps)
+    .ok_or(format!(
+        "Best x step {} (mod {}) and y step {} (mod {}) never line up",
+        step_x, width, step_y, height
+    ))
+}
 
-        let largest_cluster = get_largest_cluster(&robot_positions);
+// Returns the step within the period with the smallest spread of the coordinates along one axis
+fn find_most_ordered_step(axis: &[(i32, i32)], period: i32, metric: SpreadMetric) -> i32 {
+    let mut best_step = 0;
+    let mut best_spread = f64::INFINITY;
+    for steps in 0..period {
+        let coordinates: Vec<i32> = axis
+            .iter()
+            .map(|(position, velocity)| (position + steps * velocity).rem_euclid(period))
+            .collect();
+        let spread = calc_spread(&coordinates, period, metric);
+        if spread < best_spread {
+            best_spread = spread;
+            best_step = steps;
+        }
+    }
+    best_step
+}
 
-        if largest_cluster >= cluster_size_threshold {
-            // visualize(&robot_positions, width, height);
-            return steps.to_string();
+fn calc_spread(coordinates: &[i32], period: i32, metric: SpreadMetric) -> f64 {
+    let n = coordinates.len() as f64;
+    match metric {
+        SpreadMetric::Variance => {
+            let mean = coordinates.iter().map(|c| *c as f64).sum::<f64>() / n;
+            coordinates
+                .iter()
+                .map(|c| (*c as f64 - mean).powi(2))
+                .sum::<f64>()
+                / n
+        }
+        SpreadMetric::Entropy => {
+            let mut histogram = vec![0; period as usize];
+            for c in coordinates {
+                histogram[*c as usize] += 1;
+            }
+            histogram
+                .iter()
+                .filter(|count| **count > 0)
+                .map(|count| {
+                    let p = *count as f64 / n;
+                    -p * p.ln()
+                })
+                .sum()
         }
     }
+}
 
-    "No solution in step: [0,20000] with cluster_threshold: 115".to_string()
+// Solves t = a_1 (mod m_1), t = a_2 (mod m_2) and returns the smallest t >= 0 together with the
+// combined modulus. The moduli don't have to be coprime, but then a solution may not exist.
+fn chinese_remainder((a_1, m_1): (i64, i64), (a_2, m_2): (i64, i64)) -> Option<(i64, i64)> {
+    let (g, p, _) = extended_gcd(m_1, m_2);
+    if (a_2 - a_1) % g != 0 {
+        return None;
+    }
+    let lcm = m_1 / g * m_2;
+    let k = ((a_2 - a_1) / g * p).rem_euclid(m_2 / g);
+    Some(((a_1 + m_1 * k).rem_euclid(lcm), lcm))
+}
+
+// Returns (g, x, y) with a * x + b * y = g
+fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - a / b * y)
+    }
 }
 
 fn simulate_robot(robot: &Robot, width: i32, height: i32, steps: i32) -> Position {
@@ -73,57 +186,21 @@ fn simulate_robot(robot: &Robot, width: i32, height: i32, steps: i32) -> Positio
     (new_x, new_y)
 }
 
-fn get_largest_cluster(positions: &HashSet<Position>) -> i32 {
-    let pos_copy = positions.clone();
-    let mut largest_cluster = 0;
-
-    let mut visited: HashSet<Position> = HashSet::new();
-
-    for position in pos_copy {
-        let mut open_set: VecDeque<Position> = VecDeque::from(vec![position]);
-        let mut cluster_size = 0;
-        while !open_set.is_empty() {
-            let current_position = open_set.pop_front().unwrap();
-            if visited.contains(&current_position) {
-                continue;
-            } else {
-                visited.insert(current_position);
-                cluster_size += 1;
-                // Add neighbors to open set
-                let possible_neighbors = vec![
-                    (current_position.0 + 1, current_position.1 + 1),
-                    (current_position.0 + 1, current_position.1 - 1),
-                    (current_position.0 - 1, current_position.1 + 1),
-                    (current_position.0 - 1, current_position.1 - 1),
-                ];
-                for possible_neighbor in possible_neighbors {
-                    if positions.contains(&possible_neighbor)
-                        && !visited.contains(&possible_neighbor)
-                    {
-                        open_set.push_back(possible_neighbor);
-                    }
-                }
-            }
-        }
-        largest_cluster = max(largest_cluster, cluster_size);
-    }
-
-    largest_cluster
-}
-
-#[allow(dead_code)]
-fn visualize(robot_positions: &HashSet<Position>, width: i32, height: i32) {
+fn visualize(robot_positions: &HashSet<Position>, width: i32, height: i32) -> String {
+    let mut lines: Vec<String> = vec![];
     for y in 0..height {
+        let mut line = String::new();
         for x in 0..width {
             let has_pos_robot = robot_positions.contains(&((x, y)));
             if has_pos_robot {
-                print!("\x1b[38;2;76;175;80m#\x1b[0m");
+                line.push_str("\x1b[38;2;76;175;80m#\x1b[0m");
             } else {
-                print!(" ");
+                line.push(' ');
             }
         }
-        println!();
+        lines.push(line);
     }
+    lines.join("\n")
 }
 
 fn parse_puzzle(input: &str) -> Puzzle {
@@ -153,5 +230,47 @@ mod tests {
         assert_eq!(solution, "12");
     }
     #[test]
-    fn test_solve_part_two() {}
+    fn test_solve_part_two() {
+        // Robots forming a 9x9 block at step 6789, surrounded by noise
+        let (width, height, picture_step) = (101, 103, 6789);
+        // Quadratic sequences modulo a prime spread the values without obvious patterns
+        let scatter = |i: i32, a: i32, b: i32, modulus: i32| (a * i * i + b * i) % modulus;
+        let mut puzzle: Puzzle = vec![];
+        for i in 0..300 {
+            let velocity = (
+                scatter(i, 31, 17, 211) % 201 - 100,
+                scatter(i, 13, 59, 223) % 201 - 100,
+            );
+            let target = if i < 81 {
+                (40 + i % 9, 50 + i / 9)
+            } else {
+                (scatter(i, 7, 11, width), scatter(i, 5, 23, height))
+            };
+            // Move the robot back in time from its place in the picture
+            let start = simulate_robot(
+                &(target, (-velocity.0, -velocity.1)),
+                width,
+                height,
+                picture_step,
+            );
+            puzzle.push((start, velocity));
+        }
+
+        assert_eq!(
+            solve_part_two(width, height, &puzzle),
+            picture_step.to_string()
+        );
+        assert_eq!(
+            find_easter_egg(width, height, &puzzle, SpreadMetric::Entropy),
+            Ok(picture_step as i64)
+        );
+    }
+
+    #[test]
+    fn test_chinese_remainder() {
+        assert_eq!(chinese_remainder((2, 3), (3, 5)), Some((8, 15)));
+        assert_eq!(chinese_remainder((4, 6), (2, 8)), Some((10, 24)));
+        assert_eq!(chinese_remainder((1, 6), (2, 8)), None);
+        assert_eq!(chinese_remainder((0, 101), (0, 103)), Some((0, 10403)));
+    }
 }
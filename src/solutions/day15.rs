@@ -1,4 +1,7 @@
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+
 pub fn solve(input_file_path: &str) -> (String, String) {
     let input = std::fs::read_to_string(input_file_path).unwrap();
 
@@ -19,7 +22,7 @@ fn solve_part_one(mut ware_house: Warehouse, instructions: &Vec<Instruction>) ->
 }
 
 fn solve_part_two(mut ware_house: Warehouse, instructions: &Vec<Instruction>) -> String {
-    ware_house.scale();
+    ware_house.scale(2, 1);
 
     for &instruction in instructions {
         ware_house.move_robot(instruction);
@@ -86,6 +89,8 @@ struct Warehouse {
     robot: Position,
     walls: HashSet<Position>,
     boxes: HashMap<i32, WarehouseBox>,
+    // Id of the box covering a cell, kept in sync with `boxes` on every move
+    box_index: HashMap<Position, i32>,
 }
 
 impl Warehouse {
@@ -101,9 +106,18 @@ impl Warehouse {
         // Next pos has a box try to move it
         if let Some(ware_house_box) = self.get_box_at_pos(&next_position) {
             let mut affected_boxes: HashSet<WarehouseBox> = HashSet::new();
-            if self.is_box_pushable(&ware_house_box, &direction, &mut affected_boxes) {
+            if self.is_box_pushable(ware_house_box, &direction, &mut affected_boxes) {
+                // Clear all old cells first, pushed boxes may move into each others cells
+                for boxes_to_push in affected_boxes.iter() {
+                    for position in boxes_to_push.get_all_positions(0, 0) {
+                        self.box_index.remove(&position);
+                    }
+                }
                 for mut boxes_to_push in affected_boxes {
                     boxes_to_push.push(instruction);
+                    for position in boxes_to_push.get_all_positions(0, 0) {
+                        self.box_index.insert(position, boxes_to_push.id);
+                    }
                     self.boxes.insert(boxes_to_push.id, boxes_to_push);
                 }
                 self.robot = next_position;
@@ -119,13 +133,15 @@ impl Warehouse {
             .iter()
             .for_each(|(x, y)| grid[(y * self.width + x) as usize] = '#');
 
-        self.boxes.iter().for_each(|(_, b)| {
-            let (x, y) = b.position;
-            if b.width == 1 {
-                grid[(y * self.width + x) as usize] = 'O';
-            } else {
-                grid[(y * self.width + x) as usize] = '[';
-                grid[(y * self.width + x + 1) as usize] = ']';
+        self.boxes.values().for_each(|b| {
+            for (x, y) in b.get_all_positions(0, 0) {
+                let symbol = match (b.width, x - b.position.0) {
+                    (1, _) => 'O',
+                    (_, 0) => '[',
+                    (w, dx) if dx == w - 1 => ']',
+                    _ => '=',
+                };
+                grid[(y * self.width + x) as usize] = symbol;
             }
         });
         grid[(self.robot.1 * self.width + self.robot.0) as usize] = '@';
@@ -139,17 +155,15 @@ impl Warehouse {
     }
 
     fn get_box_at_pos(&self, position: &Position) -> Option<&WarehouseBox> {
-        for warehouse_box in self.boxes.values() {
-            let (x, y) = warehouse_box.position;
-            for sx in 0..warehouse_box.width {
-                for sy in 0..warehouse_box.height {
-                    if *position == (x + sx, y + sy) {
-                        return Some(warehouse_box);
-                    }
-                }
-            }
-        }
-        None
+        self.box_index.get(position).map(|id| &self.boxes[id])
+    }
+
+    fn rebuild_box_index(&mut self) {
+        self.box_index = self
+            .boxes
+            .values()
+            .flat_map(|b| b.get_all_positions(0, 0).into_iter().map(|p| (p, b.id)))
+            .collect();
     }
 
     fn is_box_pushable(
@@ -186,33 +200,99 @@ impl Warehouse {
             .sum()
     }
 
-    fn scale(&mut self) {
-        self.width *= 2;
+    // Stretches every cell of the map to `factor_x` x `factor_y` cells. Boxes grow
+    // accordingly, while the robot keeps its size and stays in the top left corner of its cell.
+    fn scale(&mut self, factor_x: i32, factor_y: i32) {
+        self.width *= factor_x;
+        self.height *= factor_y;
 
         // Scale walls
         let mut scaled_walls: HashSet<Position> = HashSet::new();
         self.walls.iter().for_each(|pos| {
-            let left = (pos.0 * 2, pos.1);
-            let right = (left.0 + 1, left.1);
-            scaled_walls.insert(left);
-            scaled_walls.insert(right);
+            for sx in 0..factor_x {
+                for sy in 0..factor_y {
+                    scaled_walls.insert((pos.0 * factor_x + sx, pos.1 * factor_y + sy));
+                }
+            }
         });
 
         self.boxes = HashMap::from_iter(self.boxes.iter().map(|(id, b)| {
             let (bx, by) = b.position;
             let scaled_box = WarehouseBox {
                 id: *id,
-                position: (bx * 2, by),
-                width: 2,
-                height: 1,
+                position: (bx * factor_x, by * factor_y),
+                width: b.width * factor_x,
+                height: b.height * factor_y,
             };
             (*id, scaled_box)
         }));
 
-        self.robot = (self.robot.0 * 2, self.robot.1);
+        self.robot = (self.robot.0 * factor_x, self.robot.1 * factor_y);
         self.walls = scaled_walls;
+        self.rebuild_box_index();
     }
 }
+
+// Maps a key press to an instruction. Accepts the arrow keys (ANSI escape sequences) as well
+// as the instruction symbols of the puzzle input.
+fn key_to_instruction(key: &[u8]) -> Option<Instruction> {
+    match key {
+        [0x1b, b'[', b'A'] | [b'^'] => Some(Instruction::North),
+        [0x1b, b'[', b'C'] | [b'>'] => Some(Instruction::East),
+        [0x1b, b'[', b'B'] | [b'v'] => Some(Instruction::South),
+        [0x1b, b'[', b'D'] | [b'<'] => Some(Instruction::West),
+        _ => None,
+    }
+}
+
+// Lets the user steer the robot through the scaled warehouse with the arrow keys, 'q' quits
+pub fn play(input_file_path: &str) {
+    let input = std::fs::read_to_string(input_file_path).unwrap();
+    let (mut ware_house, _) = parse_input(&input);
+    ware_house.scale(2, 1);
+
+    // Read single key presses without waiting for enter and without echoing them
+    let set_terminal_mode = |args: &[&str]| {
+        let _ = Command::new("stty")
+            .args(args)
+            .stdin(Stdio::inherit())
+            .stderr(Stdio::null())
+            .status();
+    };
+    set_terminal_mode(&["-icanon", "-echo"]);
+
+    let mut stdin = std::io::stdin().lock();
+    let mut moves = 0;
+    loop {
+        print!("\x1b[2J\x1b[H");
+        ware_house.visualise_state();
+        println!(
+            "Moves: {} GPS: {} (Arrow keys to move, q to quit)",
+            moves,
+            ware_house.sum_of_gps()
+        );
+        let _ = std::io::stdout().flush();
+
+        let mut key = [0u8; 3];
+        let key = match stdin.read(&mut key[..1]) {
+            Ok(1) if key[0] == 0x1b => match stdin.read_exact(&mut key[1..]) {
+                Ok(()) => &key[..],
+                Err(_) => break,
+            },
+            Ok(1) => &key[..1],
+            _ => break,
+        };
+        if key == b"q" {
+            break;
+        }
+        if let Some(instruction) = key_to_instruction(key) {
+            ware_house.move_robot(instruction);
+            moves += 1;
+        }
+    }
+
+    set_terminal_mode(&["icanon", "echo"]);
+}
 #[rustfmt::skip]
 fn parse_input(input: &str) -> (Warehouse, Vec<Instruction>) {
     let width = input.lines().next().unwrap().len() as i32;
@@ -265,7 +345,8 @@ fn parse_input(input: &str) -> (Warehouse, Vec<Instruction>) {
         (id as i32, warehouse_box)
     }));
 
-    let ware_house = Warehouse {width, height, robot:robot_position, walls, boxes};
+    let mut ware_house = Warehouse {width, height, robot:robot_position, walls, boxes, box_index: HashMap::new()};
+    ware_house.rebuild_box_index();
 
     (ware_house, instructions)
 }
@@ -288,4 +369,51 @@ mod tests {
         let solution = solve_part_two(w, &i);
         assert_eq!(solution, "9021");
     }
+
+    #[test]
+    fn test_small_warehouses() {
+        let input = "########\n#..O.O.#\n##@.O..#\n#...O..#\n#.#.O..#\n#...O..#\n#......#\n########\n\n<^^>>>vv<v>>v<<";
+        let (w, i) = parse_input(input);
+        assert_eq!(solve_part_one(w, &i), "2028");
+
+        let input = "#######\n#...#.#\n#.....#\n#..OO@#\n#..O..#\n#.....#\n#######\n\n<vv<<^^<<^^";
+        let (w, i) = parse_input(input);
+        assert_eq!(solve_part_two(w, &i), "618");
+    }
+
+    #[test]
+    fn test_tall_boxes() {
+        let input = "#######\n#.....#\n#..O..#\n#..@..#\n#.....#\n#######\n\n^^^";
+        let (mut w, i) = parse_input(input);
+        w.scale(2, 2);
+        assert_eq!((w.width, w.height), (14, 12));
+        assert_eq!(w.robot, (6, 6));
+        assert_eq!(
+            w.get_box_at_pos(&(7, 5)).map(|b| (b.width, b.height)),
+            Some((2, 2))
+        );
+
+        for &instruction in &i {
+            w.move_robot(instruction);
+        }
+        assert_eq!(w.robot, (6, 4));
+        assert_eq!(w.sum_of_gps(), 206);
+        assert_eq!(w.box_index.len(), 4);
+        assert!(w.get_box_at_pos(&(7, 3)).is_some());
+        assert!(w.get_box_at_pos(&(7, 4)).is_none());
+    }
+
+    #[test]
+    fn test_key_to_instruction() {
+        assert!(matches!(
+            key_to_instruction(b"\x1b[A"),
+            Some(Instruction::North)
+        ));
+        assert!(matches!(
+            key_to_instruction(b"\x1b[D"),
+            Some(Instruction::West)
+        ));
+        assert!(matches!(key_to_instruction(b">"), Some(Instruction::East)));
+        assert!(key_to_instruction(b"x").is_none());
+    }
 }
@@ -1,5 +1,6 @@
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::collections::{BinaryHeap, HashSet};
+use std::path::Path;
 
 pub fn solve(input_path: &str) -> (String, String) {
     let input = std::fs::read_to_string(input_path).unwrap();
@@ -8,6 +9,46 @@ pub fn solve(input_path: &str) -> (String, String) {
     (s1.to_string(), s2.to_string())
 }
 
+const LISTED_PATHS: usize = 5;
+const ALTERNATIVE_PATHS: usize = 3;
+
+// Shows the best seats, lists optimal and alternative paths and writes the best seats as image
+// next to the input
+pub fn explain(input_path: &str) -> Result<String, String> {
+    let input = std::fs::read_to_string(input_path).map_err(|e| e.to_string())?;
+    let labyrinth = parse(&input);
+    let move_costs = MoveCosts::default();
+    let paths = solve_maze(&labyrinth, &move_costs)?;
+    let seats = paths.best_seats();
+
+    let mut lines = vec![
+        render_with_seats(&labyrinth, &seats),
+        format!(
+            "Lowest score {} with {} best seats",
+            paths.cost,
+            seats.len()
+        ),
+        "Optimal paths:".to_string(),
+    ];
+    let mut sequences = paths.direction_sequences();
+    for sequence in sequences.by_ref().take(LISTED_PATHS) {
+        lines.push(format_directions(&sequence));
+    }
+    if sequences.next().is_some() {
+        lines.push("...".to_string());
+    }
+    lines.push(format!("{} cheapest paths:", ALTERNATIVE_PATHS));
+    for (costs, directions) in k_shortest_paths(&labyrinth, &move_costs, ALTERNATIVE_PATHS)? {
+        lines.push(format!("{} {}", costs, format_directions(&directions)));
+    }
+
+    let image_path = Path::new(input_path).with_file_name("best_seats.ppm");
+    std::fs::write(&image_path, export_seats_ppm(&labyrinth, &seats, 4))
+        .map_err(|e| e.to_string())?;
+    lines.push(format!("Best seats written to {}", image_path.display()));
+    Ok(lines.join("\n"))
+}
+
 #[derive(Debug)]
 struct Labyrinth {
     width: usize,
@@ -19,6 +60,9 @@ struct Labyrinth {
 
 impl Labyrinth {
     fn is_wall(&self, (x, y): (i32, i32)) -> bool {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return true;
+        }
         self.walls[(y * (self.width as i32) + x) as usize]
     }
 
@@ -26,12 +70,33 @@ impl Labyrinth {
         (x, y) == self.end_position
     }
 
-    fn direct_distance_to_end(&self, (x, y): (i32, i32)) -> i32 {
-        let (dx, dy) = (self.end_position.0 - x, self.end_position.1 - y);
-        ((dx * dx + dy * dy) as f64).sqrt() as i32
+    // Nodes are numbered by tile and orientation, so that the search can use plain vectors
+    fn node_count(&self) -> usize {
+        self.width * self.height * 4
+    }
+
+    fn node_index(&self, node: &Node) -> usize {
+        let (x, y) = node.position;
+        (y as usize * self.width + x as usize) * 4 + node.orientation.index()
+    }
+
+    fn node_at(&self, index: usize) -> Node {
+        let tile = index / 4;
+        Node {
+            position: ((tile % self.width) as i32, (tile / self.width) as i32),
+            orientation: Direction::ALL[index % 4],
+        }
+    }
+
+    fn start_node(&self) -> Node {
+        Node {
+            position: self.start_position,
+            orientation: Direction::East,
+        }
     }
 }
-#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, PartialOrd, Ord)]
 enum Direction {
     North,
     South,
@@ -40,6 +105,17 @@ enum Direction {
 }
 
 impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+    ];
+
+    fn index(&self) -> usize {
+        Direction::ALL.iter().position(|d| d == self).unwrap()
+    }
+
     fn to_direction(&self) -> (i32, i32) {
         match self {
             Direction::North => (0, -1),
@@ -48,6 +124,17 @@ impl Direction {
             Direction::West => (-1, 0),
         }
     }
+
+    // Same symbols as the robot moves of day 15
+    fn symbol(self) -> char {
+        match self {
+            Direction::North => '^',
+            Direction::South => 'v',
+            Direction::East => '>',
+            Direction::West => '<',
+        }
+    }
+
     fn rot_clockwise(&self) -> Direction {
         match self {
             Direction::North => Direction::East,
@@ -64,9 +151,63 @@ impl Direction {
             Direction::East => Direction::North,
         }
     }
+
+    fn quarter_turns_to(&self, other: &Direction) -> i32 {
+        if self == other {
+            0
+        } else if self.rot_clockwise() == *other || self.rot_anti_clockwise() == *other {
+            1
+        } else {
+            2
+        }
+    }
+}
+
+fn format_directions(directions: &[Direction]) -> String {
+    directions.iter().map(|d| d.symbol()).collect()
+}
+
+// Costs of a single step forward and of a quarter turn on the spot
+#[derive(Debug, Clone, Copy)]
+struct MoveCosts {
+    step: i32,
+    turn: i32,
+}
+
+impl Default for MoveCosts {
+    fn default() -> Self {
+        MoveCosts {
+            step: 1,
+            turn: 1000,
+        }
+    }
+}
+
+impl MoveCosts {
+    fn validate(&self) -> Result<(), String> {
+        // Free steps would allow cycles of equal cost in the predecessor graph
+        if self.step <= 0 || self.turn < 0 {
+            return Err(format!(
+                "Invalid move costs: step {} has to be positive and turn {} must not be negative",
+                self.step, self.turn
+            ));
+        }
+        // A move turns at most twice, whole paths are checked for overflow during the search
+        if self.step as i64 + 2 * self.turn as i64 > i32::MAX as i64 {
+            return Err(format!(
+                "Move costs step {} and turn {} overflow in a single move",
+                self.step, self.turn
+            ));
+        }
+        Ok(())
+    }
+
+    fn of_move(&self, from: &Direction, to: &Direction) -> i32 {
+        self.step + self.turn * from.quarter_turns_to(to)
+    }
 }
 
-#[derive(Clone, Hash, Eq, PartialEq, Debug)]
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Debug)]
 struct Node {
     position: (i32, i32),
     orientation: Direction,
@@ -75,7 +216,7 @@ struct Node {
 #[derive(Clone, Eq, PartialEq, Debug)]
 struct Priority {
     priority: i32,
-    node: Node,
+    node: usize,
 }
 
 impl PartialOrd<Self> for Priority {
@@ -90,147 +231,295 @@ impl Ord for Priority {
     }
 }
 
-fn find_shortest_paths(labyrinth: &Labyrinth) -> Option<(i32, i32)> {
-    let mut open_list = BinaryHeap::<Priority>::new();
-    let mut costs: HashMap<Node, i32> = HashMap::new();
-    let mut predecessors: HashMap<Node, HashSet<Node>> = HashMap::new();
+// Result of a Dijkstra search. Every node keeps all predecessors on its cheapest paths, which
+// forms a DAG of all optimal paths to the end.
+struct SearchTree {
+    start: usize,
+    predecessors: Vec<Vec<usize>>,
+    end_cost: Option<i32>,
+    end_nodes: Vec<usize>,
+}
 
-    let start_node = Node {
-        position: labyrinth.start_position,
-        orientation: Direction::East,
-    };
+impl SearchTree {
+    // One cheapest path as node indices from the start to the end
+    fn cheapest_path(&self) -> Option<Vec<usize>> {
+        let mut node = *self.end_nodes.first()?;
+        let mut path = vec![node];
+        while node != self.start {
+            node = self.predecessors[node][0];
+            path.push(node);
+        }
+        path.reverse();
+        Some(path)
+    }
+}
 
-    costs.insert(start_node.clone(), 0);
+fn search(
+    labyrinth: &Labyrinth,
+    move_costs: &MoveCosts,
+    start: usize,
+    is_allowed: &dyn Fn(usize, usize) -> bool,
+) -> Result<SearchTree, String> {
+    let mut open_list = BinaryHeap::<Priority>::new();
+    let mut costs = vec![i32::MAX; labyrinth.node_count()];
+    let mut predecessors: Vec<Vec<usize>> = vec![vec![]; labyrinth.node_count()];
+    let mut end_cost: Option<i32> = None;
+    let mut end_nodes: Vec<usize> = vec![];
 
+    costs[start] = 0;
     open_list.push(Priority {
         priority: 0,
-        node: start_node,
+        node: start,
     });
 
-    let mut shortest_path_costs = i32::MAX;
-    let mut unique_tiles: HashSet<(i32, i32)> = HashSet::new();
-
-    while !open_list.is_empty() {
-        let node = open_list.pop().unwrap().node;
+    while let Some(Priority { priority, node }) = open_list.pop() {
+        if priority > costs[node] {
+            continue;
+        }
+        // Everything left is more expensive than the best way to the end
+        if end_cost.is_some_and(|end_cost| priority > end_cost) {
+            break;
+        }
 
-        if labyrinth.is_goal(node.position) {
-            let total_path_costs = costs.get(&node).unwrap();
+        let current = labyrinth.node_at(node);
+        if labyrinth.is_goal(current.position) {
+            end_cost = Some(priority);
+            end_nodes.push(node);
+            continue;
+        }
 
-            // Keep collecting solutions while they are optimal
-            if total_path_costs <= &shortest_path_costs {
-                shortest_path_costs = total_path_costs.clone();
-                let paths = extract_paths(node.clone(), &predecessors, Vec::new());
-                for path_nodes in paths {
-                    for tile in path_nodes {
-                        unique_tiles.insert(tile.position);
-                    }
+        for (successor, cost) in get_successors(&current, labyrinth, move_costs) {
+            let next = labyrinth.node_index(&successor);
+            if !is_allowed(node, next) {
+                continue;
+            }
+            let tentative_cost = priority.checked_add(cost).ok_or("Path costs overflow")?;
+            match tentative_cost.cmp(&costs[next]) {
+                Ordering::Less => {
+                    costs[next] = tentative_cost;
+                    predecessors[next] = vec![node];
+                    open_list.push(Priority {
+                        priority: tentative_cost,
+                        node: next,
+                    });
                 }
-            } else {
-                // print_with_paths(&labyrinth, &unique_tiles);
-                return Some((shortest_path_costs, unique_tiles.len() as i32));
+                Ordering::Equal => predecessors[next].push(node),
+                Ordering::Greater => {}
             }
         }
+    }
+
+    Ok(SearchTree {
+        start,
+        predecessors,
+        end_cost,
+        end_nodes,
+    })
+}
 
-        // Expand node
-        for (successor, cost) in get_successors(&node, &labyrinth) {
-            let tentative_cost = costs.get(&node).unwrap() + cost;
+struct OptimalPaths<'a> {
+    labyrinth: &'a Labyrinth,
+    cost: i32,
+    tree: SearchTree,
+}
 
-            // This branch is too expensive
-            if let Some(known_costs) = costs.get(&successor) {
-                if known_costs < &tentative_cost {
-                    continue;
-                }
+impl OptimalPaths<'_> {
+    // Tiles on at least one optimal path
+    fn best_seats(&self) -> HashSet<(i32, i32)> {
+        let mut visited = vec![false; self.labyrinth.node_count()];
+        let mut stack = self.tree.end_nodes.clone();
+        let mut seats: HashSet<(i32, i32)> = HashSet::new();
+        while let Some(node) = stack.pop() {
+            if visited[node] {
+                continue;
             }
+            visited[node] = true;
+            seats.insert(self.labyrinth.node_at(node).position);
+            stack.extend(&self.tree.predecessors[node]);
+        }
+        seats
+    }
 
-            let pred = predecessors
-                .entry(successor.clone())
-                .or_insert(HashSet::new());
-            pred.insert(node.clone());
+    // Every optimal path as the directions of its steps. The number of paths can grow
+    // exponentially with the size of open areas, so they are enumerated lazily and
+    // `best_seats` doesn't depend on them.
+    fn direction_sequences(&self) -> impl Iterator<Item = Vec<Direction>> + '_ {
+        let mut stack: Vec<(usize, Vec<Direction>)> = self
+            .tree
+            .end_nodes
+            .iter()
+            .map(|&node| (node, vec![]))
+            .collect();
+        std::iter::from_fn(move || {
+            while let Some((node, mut suffix)) = stack.pop() {
+                if node == self.tree.start {
+                    suffix.reverse();
+                    return Some(suffix);
+                }
+                suffix.push(self.labyrinth.node_at(node).orientation);
+                for &predecessor in &self.tree.predecessors[node] {
+                    stack.push((predecessor, suffix.clone()));
+                }
+            }
+            None
+        })
+    }
+}
 
-            costs.insert(successor.clone(), tentative_cost);
+fn solve_maze<'a>(
+    labyrinth: &'a Labyrinth,
+    move_costs: &MoveCosts,
+) -> Result<OptimalPaths<'a>, String> {
+    move_costs.validate()?;
+    let start = labyrinth.node_index(&labyrinth.start_node());
+    let tree = search(labyrinth, move_costs, start, &|_, _| true)?;
+    let cost = tree
+        .end_cost
+        .ok_or("The end can't be reached from the start")?;
+    Ok(OptimalPaths {
+        labyrinth,
+        cost,
+        tree,
+    })
+}
 
-            let priority = tentative_cost + labyrinth.direct_distance_to_end(successor.position);
+fn find_shortest_paths(labyrinth: &Labyrinth) -> Option<(i32, i32)> {
+    let paths = solve_maze(labyrinth, &MoveCosts::default()).ok()?;
+    Some((paths.cost, paths.best_seats().len() as i32))
+}
 
-            open_list.push(Priority {
-                priority,
-                node: successor,
+// The k cheapest paths that don't visit a tile twice, cheapest first (Yen's algorithm). Paths
+// of equal cost count as alternatives of each other.
+fn k_shortest_paths(
+    labyrinth: &Labyrinth,
+    move_costs: &MoveCosts,
+    k: usize,
+) -> Result<Vec<(i32, Vec<Direction>)>, String> {
+    let optimal_paths = solve_maze(labyrinth, move_costs)?;
+    if k == 0 {
+        return Ok(vec![]);
+    }
+    let path_cost = |path: &[usize]| -> Result<i32, String> {
+        path.windows(2)
+            .try_fold(0_i32, |costs, pair| {
+                let (from, to) = (labyrinth.node_at(pair[0]), labyrinth.node_at(pair[1]));
+                costs.checked_add(move_costs.of_move(&from.orientation, &to.orientation))
             })
+            .ok_or("Path costs overflow".to_string())
+    };
+
+    let mut accepted: Vec<Vec<usize>> = optimal_paths.tree.cheapest_path().into_iter().collect();
+    let mut candidates: Vec<(i32, Vec<usize>)> = vec![];
+    while accepted.len() < k {
+        let previous = accepted.last().unwrap().clone();
+        for spur_index in 0..previous.len() - 1 {
+            let root = &previous[..=spur_index];
+            // Leave the root on a way none of the accepted paths with the same root took
+            let blocked_moves: HashSet<(usize, usize)> = accepted
+                .iter()
+                .filter(|path| path.len() > spur_index + 1 && &path[..=spur_index] == root)
+                .map(|path| (path[spur_index], path[spur_index + 1]))
+                .collect();
+            let blocked_tiles: HashSet<(i32, i32)> = root
+                .iter()
+                .map(|&node| labyrinth.node_at(node).position)
+                .collect();
+            let is_allowed = |from: usize, to: usize| {
+                !blocked_moves.contains(&(from, to))
+                    && !blocked_tiles.contains(&labyrinth.node_at(to).position)
+            };
+
+            let spur_tree = search(labyrinth, move_costs, root[spur_index], &is_allowed)?;
+            if let Some(spur_path) = spur_tree.cheapest_path() {
+                let mut path = root[..spur_index].to_vec();
+                path.extend(spur_path);
+                if !accepted.contains(&path) && !candidates.iter().any(|(_, c)| *c == path) {
+                    candidates.push((path_cost(&path)?, path));
+                }
+            }
         }
+
+        // Ties are broken by the path itself, which keeps the order stable
+        let Some(best) = (0..candidates.len()).min_by(|&a, &b| candidates[a].cmp(&candidates[b]))
+        else {
+            break;
+        };
+        accepted.push(candidates.swap_remove(best).1);
     }
-    None
+
+    accepted
+        .iter()
+        .map(|path| {
+            let directions = path[1..]
+                .iter()
+                .map(|&node| labyrinth.node_at(node).orientation)
+                .collect();
+            Ok((path_cost(path)?, directions))
+        })
+        .collect()
 }
 
-#[allow(dead_code)]
-fn print_with_paths(labyrinth: &Labyrinth, seats: &HashSet<(i32, i32)>) {
+fn render_with_seats(labyrinth: &Labyrinth, seats: &HashSet<(i32, i32)>) -> String {
+    let mut lines: Vec<String> = vec![];
     for y in 0..labyrinth.height {
+        let mut line = String::new();
         for x in 0..labyrinth.width {
             let pos = (x as i32, y as i32);
             if labyrinth.is_wall(pos) {
-                print!("#");
+                line.push('#');
             } else if seats.contains(&pos) {
-                print!("\x1b[38;2;76;175;80mO\x1b[0m");
+                line.push_str("\x1b[38;2;76;175;80mO\x1b[0m");
             } else {
-                print!(".")
+                line.push('.');
             }
         }
-        println!();
+        lines.push(line);
     }
+    lines.join("\n")
 }
 
-fn extract_paths(
-    start_node: Node,
-    predecessors: &HashMap<Node, HashSet<Node>>,
-    mut acc: Vec<Node>,
-) -> Vec<Vec<Node>> {
-    acc.push(start_node.clone());
-    match predecessors.get(&start_node) {
-        None => vec![acc],
-        Some(set) => {
-            let mut sub_paths: Vec<Vec<Node>> = Vec::new();
-            for pred in set.iter() {
-                let sub_paths_for_pred = extract_paths(pred.clone(), predecessors, Vec::new());
-                for sub_sub_path in sub_paths_for_pred {
-                    let mut a_s = acc.clone();
-                    a_s.extend(sub_sub_path.clone());
-                    sub_paths.push(a_s);
-                }
-            }
-            sub_paths
+// Binary PPM image of the labyrinth with every tile drawn as `scale` x `scale` pixels and the
+// best seats in the same green as the terminal output
+fn export_seats_ppm(labyrinth: &Labyrinth, seats: &HashSet<(i32, i32)>, scale: usize) -> Vec<u8> {
+    let (image_width, image_height) = (labyrinth.width * scale, labyrinth.height * scale);
+    let mut image = format!("P6\n{} {}\n255\n", image_width, image_height).into_bytes();
+    for py in 0..image_height {
+        for px in 0..image_width {
+            let position = ((px / scale) as i32, (py / scale) as i32);
+            let colour: [u8; 3] = if labyrinth.is_wall(position) {
+                [40, 40, 40]
+            } else if position == labyrinth.start_position || labyrinth.is_goal(position) {
+                [229, 57, 53]
+            } else if seats.contains(&position) {
+                [76, 175, 80]
+            } else {
+                [255, 255, 255]
+            };
+            image.extend(colour);
         }
     }
+    image
 }
 
-fn get_successors(node: &Node, labyrinth: &Labyrinth) -> Vec<(Node, i32)> {
-    let mut successors: Vec<(Node, i32)> = vec![
-        (
-            Node {
-                position: node.position,
-                orientation: node.orientation.rot_clockwise(),
-            },
-            1000,
-        ),
-        (
-            Node {
-                position: node.position,
-                orientation: node.orientation.rot_anti_clockwise(),
-            },
-            1000,
-        ),
-    ];
-    let (x, y) = node.position.clone();
-    let (dx, dy) = node.orientation.to_direction();
-    let forward_position = (x + dx, y + dy);
-
-    if !labyrinth.is_wall(forward_position) {
-        successors.push((
-            Node {
-                position: forward_position,
-                orientation: node.orientation,
-            },
-            1,
-        ));
-    }
-    successors
+// Moves turn on the spot towards any open neighbour and step onto it
+fn get_successors(node: &Node, labyrinth: &Labyrinth, move_costs: &MoveCosts) -> Vec<(Node, i32)> {
+    let (x, y) = node.position;
+    Direction::ALL
+        .iter()
+        .filter_map(|direction| {
+            let (dx, dy) = direction.to_direction();
+            let forward_position = (x + dx, y + dy);
+            (!labyrinth.is_wall(forward_position)).then(|| {
+                (
+                    Node {
+                        position: forward_position,
+                        orientation: *direction,
+                    },
+                    move_costs.of_move(&node.orientation, direction),
+                )
+            })
+        })
+        .collect()
 }
 
 fn parse(input: &str) -> Labyrinth {
@@ -273,6 +562,30 @@ fn parse(input: &str) -> Labyrinth {
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    // Walks the directions from the start and returns the costs, if they lead through the
+    // labyrinth to the end
+    fn evaluate_directions(
+        labyrinth: &Labyrinth,
+        move_costs: &MoveCosts,
+        directions: &[Direction],
+    ) -> Option<i32> {
+        let mut node = labyrinth.start_node();
+        let mut costs = 0;
+        for direction in directions {
+            let (dx, dy) = direction.to_direction();
+            let position = (node.position.0 + dx, node.position.1 + dy);
+            if labyrinth.is_wall(position) {
+                return None;
+            }
+            costs += move_costs.of_move(&node.orientation, direction);
+            node = Node {
+                position,
+                orientation: *direction,
+            };
+        }
+        labyrinth.is_goal(node.position).then_some(costs)
+    }
     #[test]
     fn test_part_one() {
         let input = std::fs::read_to_string("./resources/day16/example.txt").unwrap();
@@ -288,4 +601,130 @@ mod tests {
         let (_, l) = find_shortest_paths(&labyrinth).unwrap();
         assert_eq!(l.to_string(), "45");
     }
+
+    const FIRST_EXAMPLE: &str = "###############\n#.......#....E#\n#.#.###.#.###.#\n#.....#.#...#.#\n#.###.#####.#.#\n#.#.#.......#.#\n#.#.#####.###.#\n#...........#.#\n###.#.#####.#.#\n#...#.....#.#.#\n#.#.#.###.#.#.#\n#.....#...#.#.#\n#.###.#.#.#.#.#\n#S..#.....#...#\n###############";
+    const SECOND_EXAMPLE: &str = "#################\n#...#...#...#..E#\n#.#.#.#.#.#.#.#.#\n#.#.#.#...#...#.#\n#.#.#.#.###.#.#.#\n#...#.#.#.....#.#\n#.#.#.#.#.#####.#\n#.#...#.#.#.....#\n#.#.#####.#.###.#\n#.#.#.......#...#\n#.#.###.#####.###\n#.#.#...#.....#.#\n#.#.#.#####.###.#\n#.#.#.........#.#\n#.#.#.#########.#\n#S#.............#\n#################";
+
+    #[test]
+    fn test_optimal_paths() {
+        let labyrinth = parse(FIRST_EXAMPLE);
+        assert_eq!(find_shortest_paths(&labyrinth), Some((7036, 45)));
+        assert_eq!(
+            find_shortest_paths(&parse(SECOND_EXAMPLE)),
+            Some((11048, 64))
+        );
+
+        // Every optimal path costs the same and together they cover all best seats
+        let paths = solve_maze(&labyrinth, &MoveCosts::default()).unwrap();
+        let sequences = paths.direction_sequences().collect::<Vec<Vec<Direction>>>();
+        assert!(sequences.len() > 1);
+        let mut tiles: HashSet<(i32, i32)> = HashSet::from([labyrinth.start_position]);
+        for sequence in &sequences {
+            let costs = evaluate_directions(&labyrinth, &MoveCosts::default(), sequence);
+            assert_eq!(costs, Some(7036));
+            let mut position = labyrinth.start_position;
+            for direction in sequence {
+                let (dx, dy) = direction.to_direction();
+                position = (position.0 + dx, position.1 + dy);
+                tiles.insert(position);
+            }
+        }
+        assert_eq!(tiles, paths.best_seats());
+
+        let image = export_seats_ppm(&labyrinth, &tiles, 2);
+        let header = b"P6\n30 30\n255\n";
+        assert_eq!(&image[..header.len()], header);
+        assert_eq!(image.len(), header.len() + 30 * 30 * 3);
+    }
+
+    #[test]
+    fn test_move_costs_and_alternatives() {
+        let labyrinth = parse("#####\n#..E#\n#.#.#\n#S..#\n#####");
+        let paths = solve_maze(&labyrinth, &MoveCosts::default()).unwrap();
+        assert_eq!(paths.cost, 1004);
+        let sequences = paths
+            .direction_sequences()
+            .map(|s| format_directions(&s))
+            .collect::<Vec<String>>();
+        assert_eq!(sequences, vec![">>^^"]);
+        assert_eq!(paths.best_seats().len(), 5);
+
+        // Without turning costs both ways around the wall are optimal
+        let free_turns = MoveCosts { step: 1, turn: 0 };
+        let paths = solve_maze(&labyrinth, &free_turns).unwrap();
+        assert_eq!(paths.cost, 4);
+        assert_eq!(paths.direction_sequences().count(), 2);
+        assert_eq!(paths.best_seats().len(), 8);
+
+        let alternatives = k_shortest_paths(&labyrinth, &MoveCosts::default(), 3).unwrap();
+        let alternatives = alternatives
+            .iter()
+            .map(|(costs, directions)| (*costs, format_directions(directions)))
+            .collect::<Vec<(i32, String)>>();
+        assert_eq!(
+            alternatives,
+            vec![(1004, ">>^^".to_string()), (2004, "^^>>".to_string())]
+        );
+        assert!(k_shortest_paths(&labyrinth, &MoveCosts::default(), 0)
+            .unwrap()
+            .is_empty());
+
+        // The optimal paths of the example come first, followed by a more expensive one
+        let labyrinth = parse(FIRST_EXAMPLE);
+        let paths = solve_maze(&labyrinth, &MoveCosts::default()).unwrap();
+        let mut optimal = paths.direction_sequences().collect::<Vec<Vec<Direction>>>();
+        optimal.sort();
+        let alternatives =
+            k_shortest_paths(&labyrinth, &MoveCosts::default(), optimal.len() + 1).unwrap();
+        assert_eq!(alternatives.len(), optimal.len() + 1);
+        let mut best = alternatives[..optimal.len()]
+            .iter()
+            .map(|(costs, directions)| {
+                assert_eq!(*costs, 7036);
+                directions.clone()
+            })
+            .collect::<Vec<Vec<Direction>>>();
+        best.sort();
+        assert_eq!(best, optimal);
+        assert!(alternatives[optimal.len()].0 > 7036);
+
+        assert!(solve_maze(&labyrinth, &MoveCosts { step: 0, turn: 1 }).is_err());
+        let expensive_turns = MoveCosts {
+            step: 1,
+            turn: i32::MAX / 2,
+        };
+        assert!(solve_maze(&labyrinth, &expensive_turns).is_err());
+        let expensive_turns = MoveCosts {
+            step: 1,
+            turn: 1_000_000,
+        };
+        assert!(solve_maze(&labyrinth, &expensive_turns).is_ok());
+        assert!(solve_maze(&parse("#####\n#S#E#\n#####"), &MoveCosts::default()).is_err());
+        let expensive_moves = MoveCosts {
+            step: i32::MAX,
+            turn: 1,
+        };
+        assert!(solve_maze(&labyrinth, &expensive_moves).is_err());
+    }
+
+    #[test]
+    fn test_large_labyrinth() {
+        // 2000x150 tiles are more nodes than a worst case bound on the path costs allows, but
+        // the only way through is a corridor with two turns
+        let (width, height) = (2000, 150);
+        let mut rows = vec!["#".repeat(width); height];
+        rows[height - 2] = format!("#S{}#", ".".repeat(width - 3));
+        for row in rows[1..height - 2].iter_mut() {
+            *row = format!("{}.#", "#".repeat(width - 2));
+        }
+        rows[1] = format!("{}E#", "#".repeat(width - 2));
+        let labyrinth = parse(&rows.join("\n"));
+        assert!(labyrinth.node_count() > 1_000_000);
+
+        let corridor = (width - 3) as i32 + (height - 3) as i32;
+        assert_eq!(
+            find_shortest_paths(&labyrinth),
+            Some((corridor + 1000, corridor + 1))
+        );
+    }
 }
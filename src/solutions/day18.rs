@@ -1,4 +1,4 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{HashMap, VecDeque};
 use std::fmt::{Display, Formatter};
 
 pub fn solve(input_file_path: &str) -> (String, String) {
@@ -23,37 +23,25 @@ fn solve_part_one(maze: &Maze) -> Vec<(usize, usize)> {
 }
 
 fn solve_part_two(maze: &Maze) -> String {
-    let mut lower = 0;
-    let mut higher = maze.falling_bytes.len();
-
-    while higher - lower > 1 {
-        let mid = (higher - lower) / 2 + lower;
-        let mut m = maze.clone();
-        m.simulate(mid);
-        match m.shortest_path(m.get_start_position(), m.get_end_position()) {
-            Ok(_) => {
-                lower = mid;
-            }
-            Err(_) => {
-                higher = mid;
-            }
+    match maze.find_blocking_byte() {
+        Some(index) => {
+            let (x, y) = maze.falling_bytes[index];
+            format!("{},{}", x, y)
         }
+        None => "Exit stays reachable".to_string(),
     }
-    let blocking_byte = *maze.falling_bytes.get(lower).unwrap();
-    let res = format!("{},{}", blocking_byte.0, blocking_byte.1);
-    res
 }
 
 #[derive(Clone)]
 struct Maze {
     width: usize,
     height: usize,
-    falling_bytes: Vec<(u8, u8)>,
+    falling_bytes: Vec<(usize, usize)>,
     corrupted_grid: Vec<bool>,
 }
 
 impl Maze {
-    fn new(width: usize, height: usize, falling_bytes: Vec<(u8, u8)>) -> Maze {
+    fn new(width: usize, height: usize, falling_bytes: Vec<(usize, usize)>) -> Maze {
         Maze {
             width,
             height,
@@ -76,7 +64,7 @@ impl Maze {
     fn simulate(&mut self, ticks: usize) {
         for i in 0..ticks {
             let &(cx, cy) = self.falling_bytes.get(i).unwrap();
-            self.corrupted_grid[cy as usize * self.width + cx as usize] = true;
+            self.corrupted_grid[cy * self.width + cx] = true;
         }
     }
 
@@ -86,37 +74,31 @@ impl Maze {
         end: (usize, usize),
     ) -> Result<Vec<(usize, usize)>, String> {
         let mut open_set: VecDeque<(usize, usize)> = VecDeque::with_capacity(1000);
-        let mut closed_set: HashSet<(usize, usize)> = HashSet::with_capacity(1000);
+        let mut visited: Vec<bool> = vec![false; self.width * self.height];
         let mut pred: HashMap<(usize, usize), (usize, usize)> = HashMap::with_capacity(1000);
 
         open_set.push_back(start);
+        visited[start.1 * self.width + start.0] = true;
 
-        while !open_set.is_empty() {
-            let pos @ (x, y) = open_set.pop_front().unwrap();
-            
-            closed_set.insert(pos);
+        while let Some(pos @ (x, y)) = open_set.pop_front() {
             if pos == end {
                 // Found end node reconstruct path
-                let mut node = pos.clone();
+                let mut node = pos;
                 let mut path: Vec<(usize, usize)> = Vec::new();
                 path.push(pos);
                 while let Some(pred) = pred.get(&node) {
-                    node = pred.clone();
+                    node = *pred;
                     path.push(node);
                 }
                 return Ok(path);
             }
 
-            let n = self.get_neighbors(x, y);
-            for neighbor in n {
-                if closed_set.contains(&neighbor) {
-                    continue;
-                }
-
-                if !open_set.contains(&neighbor) {
+            for neighbor @ (nx, ny) in self.get_neighbors(x, y) {
+                if !visited[ny * self.width + nx] {
+                    visited[ny * self.width + nx] = true;
+                    pred.insert(neighbor, pos);
                     open_set.push_back(neighbor);
                 }
-                pred.insert(neighbor, pos);
             }
         }
 
@@ -143,6 +125,67 @@ impl Maze {
             .cloned()
             .collect::<Vec<(usize, usize)>>()
     }
+
+    // Index of the first byte after which the exit can't be reached anymore
+    fn find_blocking_byte(&self) -> Option<usize> {
+        let (end_x, end_y) = self.get_end_position();
+        self.unreachable_after()[end_y * self.width + end_x].map(|fallen| fallen - 1)
+    }
+
+    // Number of fallen bytes from which on each cell can't be reached from the start, `None`
+    // if the cell is still reachable after all bytes have fallen. The bytes are removed in
+    // reverse order while the free cells are joined with a union find. Whenever a component is
+    // connected to the start at time t, all its cells have been reachable up until t.
+    fn unreachable_after(&self) -> Vec<Option<usize>> {
+        let cell_count = self.width * self.height;
+        let mut first_fall: Vec<Option<usize>> = vec![None; cell_count];
+        for (time, &(x, y)) in self.falling_bytes.iter().enumerate() {
+            first_fall[y * self.width + x].get_or_insert(time);
+        }
+
+        let (start_x, start_y) = self.get_start_position();
+        let start = start_y * self.width + start_x;
+        let mut unreachable_after: Vec<Option<usize>> = vec![None; cell_count];
+        let mut components = Components::new(cell_count);
+        let mut is_free: Vec<bool> = vec![false; cell_count];
+
+        // First everything that is still free after the last byte fell, then the bytes in reverse
+        let initially_free = (0..cell_count)
+            .filter(|cell| first_fall[*cell].is_none())
+            .map(|cell| (cell, None));
+        let freed_by_bytes = (0..self.falling_bytes.len()).rev().filter_map(|time| {
+            let (x, y) = self.falling_bytes[time];
+            let cell = y * self.width + x;
+            (first_fall[cell] == Some(time)).then_some((cell, Some(time + 1)))
+        });
+
+        for (cell, time) in initially_free.chain(freed_by_bytes) {
+            is_free[cell] = true;
+            let (x, y) = (cell % self.width, cell / self.width);
+            for (nx, ny) in self.get_grid_neighbors(x, y) {
+                let neighbor = ny * self.width + nx;
+                if is_free[neighbor] {
+                    let start = is_free[start].then_some(start);
+                    components.union(cell, neighbor, start, |member| {
+                        unreachable_after[member] = time
+                    });
+                }
+            }
+            if cell == start {
+                components.drain_members(start, |member| unreachable_after[member] = time);
+            }
+        }
+        unreachable_after
+    }
+
+    fn get_grid_neighbors(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+        let (width, height) = (self.width, self.height);
+        [(1, 0), (0, 1), (-1, 0), (0, -1)]
+            .into_iter()
+            .map(move |(dx, dy)| (x as i64 + dx, y as i64 + dy))
+            .filter(move |&(nx, ny)| nx >= 0 && ny >= 0 && nx < width as i64 && ny < height as i64)
+            .map(|(nx, ny)| (nx as usize, ny as usize))
+    }
 }
 
 impl Display for Maze {
@@ -159,6 +202,71 @@ impl Display for Maze {
     }
 }
 
+// Union find over the grid cells. Every root keeps the cells of its component that haven't
+// been handed out yet, so that cells joining the start component can be reported exactly once.
+struct Components {
+    parent: Vec<usize>,
+    members: Vec<Vec<usize>>,
+}
+
+impl Components {
+    fn new(size: usize) -> Components {
+        Components {
+            parent: (0..size).collect(),
+            members: (0..size).map(|cell| vec![cell]).collect(),
+        }
+    }
+
+    fn find(&mut self, cell: usize) -> usize {
+        let mut root = cell;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        // Path compression
+        let mut cell = cell;
+        while self.parent[cell] != root {
+            let next = self.parent[cell];
+            self.parent[cell] = root;
+            cell = next;
+        }
+        root
+    }
+
+    // Joins the components of `a` and `b`. If one of them contains `start`, the members of the
+    // other one are handed to `on_reached`.
+    fn union(&mut self, a: usize, b: usize, start: Option<usize>, on_reached: impl FnMut(usize)) {
+        let (root_a, root_b) = (self.find(a), self.find(b));
+        if root_a == root_b {
+            return;
+        }
+        let start_root = start.map(|start| self.find(start));
+        if start_root == Some(root_a) || start_root == Some(root_b) {
+            let other = if start_root == Some(root_a) {
+                root_b
+            } else {
+                root_a
+            };
+            self.drain_members(other, on_reached);
+        }
+        // Merge the smaller member list into the larger one
+        let (root, child) = if self.members[root_a].len() >= self.members[root_b].len() {
+            (root_a, root_b)
+        } else {
+            (root_b, root_a)
+        };
+        self.parent[child] = root;
+        let child_members = std::mem::take(&mut self.members[child]);
+        self.members[root].extend(child_members);
+    }
+
+    fn drain_members(&mut self, cell: usize, on_reached: impl FnMut(usize)) {
+        let root = self.find(cell);
+        std::mem::take(&mut self.members[root])
+            .into_iter()
+            .for_each(on_reached);
+    }
+}
+
 fn parse_puzzle(input: &str, size: (usize, usize)) -> Maze {
     let fb = input
         .lines()
@@ -166,7 +274,7 @@ fn parse_puzzle(input: &str, size: (usize, usize)) -> Maze {
             let (l, r) = line.split_once(',').unwrap();
             (l.parse().unwrap(), r.parse().unwrap())
         })
-        .collect::<Vec<(u8, u8)>>();
+        .collect::<Vec<(usize, usize)>>();
     Maze::new(size.0, size.1, fb)
 }
 
@@ -192,4 +300,34 @@ mod tests {
         let solution = solve_part_two(&maze);
         assert_eq!(solution, "6,1");
     }
+
+    #[test]
+    fn test_unreachable_after() {
+        let input = "5,4\n4,2\n4,5\n3,0\n2,1\n6,3\n2,4\n1,5\n0,6\n3,3\n2,6\n5,1\n1,2\n5,5\n2,5\n6,5\n1,4\n0,4\n6,4\n1,1\n6,1\n1,0\n0,5\n1,6\n2,0";
+        let maze = parse_puzzle(input, (7, 7));
+        assert_eq!(solve_part_two(&maze), "6,1");
+
+        // Compare with a BFS after every byte
+        let unreachable_after = maze.unreachable_after();
+        for time in 0..=maze.falling_bytes.len() {
+            let mut m = maze.clone();
+            m.simulate(time);
+            for y in 0..7 {
+                for x in 0..7 {
+                    let reachable = !m.is_corrupted(x, y)
+                        && m.shortest_path(m.get_start_position(), (x, y)).is_ok();
+                    let expected = unreachable_after[y * 7 + x].is_none_or(|after| time < after);
+                    assert_eq!(reachable, expected, "cell {},{} at time {}", x, y, time);
+                }
+            }
+        }
+
+        // Blocking the start cuts off everything, duplicates don't matter
+        let maze = parse_puzzle("1,1\n0,0\n1,1", (3, 3));
+        assert_eq!(maze.find_blocking_byte(), Some(1));
+        assert_eq!(maze.unreachable_after()[4], Some(1));
+        assert_eq!(maze.unreachable_after()[8], Some(2));
+        let maze = parse_puzzle("1,1\n1,0", (3, 3));
+        assert_eq!(solve_part_two(&maze), "Exit stays reachable");
+    }
 }
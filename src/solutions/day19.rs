@@ -1,13 +1,39 @@
-use std::collections::HashMap;
-
 pub fn solve(input_file: &str) -> (String, String) {
     let input = std::fs::read_to_string(input_file).unwrap();
-    let (towels, target) = parse_puzzle(&*input);
+    let (towels, target) = parse_puzzle(&input);
     let solution_one = solve_part_one(&towels, &target);
     let solution_two = solve_part_two(&towels, &target);
     (solution_one.to_string(), solution_two.to_string())
 }
 
+// Shows one arrangement of every possible design and where the impossible ones get stuck
+pub fn explain(input_file: &str) -> Result<String, String> {
+    let input = std::fs::read_to_string(input_file).map_err(|e| e.to_string())?;
+    let (towels, targets) = parse_puzzle(&input);
+    let trie = TowelTrie::new(&towels);
+
+    let mut lines: Vec<String> = vec![];
+    for design in &targets {
+        if let Some(arrangement) = trie.arrangements(design).next() {
+            lines.push(format!(
+                "{} = {}, arrangements: {}",
+                design,
+                arrangement.join(" + "),
+                trie.count_arrangements(design)
+            ));
+        }
+    }
+    for (design, stuck) in trie.impossible_designs(&targets) {
+        lines.push(format!(
+            "{} is impossible, stuck after {} of {} stripes",
+            design,
+            stuck,
+            design.len()
+        ));
+    }
+    Ok(lines.join("\n"))
+}
+
 fn parse_puzzle(input: &str) -> (Vec<&str>, Vec<&str>) {
     let mut line_iter = input.lines();
     let towels = line_iter.next().unwrap().split(", ").collect::<Vec<&str>>();
@@ -16,36 +42,170 @@ fn parse_puzzle(input: &str) -> (Vec<&str>, Vec<&str>) {
     (towels, targets)
 }
 
-fn solve_part_one(towels: &Vec<&str>, targets: &Vec<&str>) -> i32 {
+fn solve_part_one(towels: &[&str], targets: &[&str]) -> i32 {
+    let trie = TowelTrie::new(towels);
     targets
         .iter()
-        .filter(|&&t| prefixes(&towels, t, &mut HashMap::new()) > 0)
+        .filter(|t| trie.count_arrangements(t) > 0)
         .count() as i32
 }
 
-fn solve_part_two(towels: &Vec<&str>, targets: &Vec<&str>) -> u64 {
-    targets
-        .iter()
-        .map(|&t| prefixes(&towels, t, &mut HashMap::new()))
-        .sum()
+fn solve_part_two(towels: &[&str], targets: &[&str]) -> u64 {
+    let trie = TowelTrie::new(towels);
+    targets.iter().map(|t| trie.count_arrangements(t)).sum()
+}
+
+#[derive(Debug, Default)]
+struct TrieNode {
+    children: Vec<(u8, usize)>,
+    // Index of the towel ending at this node
+    towel: Option<usize>,
 }
 
-fn prefixes<'a>(part: &Vec<&str>, word: &'a str, cache: &mut HashMap<&'a str, u64>) -> u64 {
-    if cache.contains_key(word) {
-        return cache[word];
+// All towel patterns in one prefix tree, so matching every towel at a position of a design is
+// a single walk down the tree
+struct TowelTrie<'a> {
+    towels: Vec<&'a str>,
+    nodes: Vec<TrieNode>,
+}
+
+impl<'a> TowelTrie<'a> {
+    fn new(towels: &[&'a str]) -> TowelTrie<'a> {
+        let mut trie = TowelTrie {
+            towels: towels.to_vec(),
+            nodes: vec![TrieNode::default()],
+        };
+        for (index, towel) in towels.iter().enumerate() {
+            let mut node = 0;
+            for stripe in towel.bytes() {
+                node = match trie.child(node, stripe) {
+                    Some(child) => child,
+                    None => {
+                        trie.nodes.push(TrieNode::default());
+                        let child = trie.nodes.len() - 1;
+                        trie.nodes[node].children.push((stripe, child));
+                        child
+                    }
+                };
+            }
+            trie.nodes[node].towel.get_or_insert(index);
+        }
+        trie
+    }
+
+    fn child(&self, node: usize, stripe: u8) -> Option<usize> {
+        self.nodes[node]
+            .children
+            .iter()
+            .find(|(s, _)| *s == stripe)
+            .map(|(_, child)| *child)
+    }
+
+    // Towels matching the design at `start` as (towel index, end position)
+    fn matches_at<'d>(
+        &'d self,
+        design: &'d str,
+        start: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + 'd {
+        let (mut node, mut position) = (0, start);
+        std::iter::from_fn(move || {
+            while position < design.len() {
+                node = self.child(node, design.as_bytes()[position])?;
+                position += 1;
+                if let Some(towel) = self.nodes[node].towel {
+                    return Some((towel, position));
+                }
+            }
+            None
+        })
+    }
+
+    // Number of arrangements of the rest of the design from every position on, the last entry
+    // is the empty rest that can be arranged in exactly one way
+    fn count_suffix_arrangements(&self, design: &str) -> Vec<u64> {
+        let mut arrangements: Vec<u64> = vec![0; design.len() + 1];
+        arrangements[design.len()] = 1;
+        for start in (0..design.len()).rev() {
+            arrangements[start] = self
+                .matches_at(design, start)
+                .map(|(_, end)| arrangements[end])
+                .sum();
+        }
+        arrangements
+    }
+
+    fn count_arrangements(&self, design: &str) -> u64 {
+        self.count_suffix_arrangements(design)[0]
+    }
+
+    // Lazily lists all towel sequences that form the design
+    fn arrangements<'d>(&'d self, design: &'d str) -> Arrangements<'a, 'd> {
+        Arrangements {
+            trie: self,
+            design,
+            suffix_arrangements: self.count_suffix_arrangements(design),
+            stack: vec![(0, vec![])],
+        }
     }
-    let mut counter = 0;
-    for prefix in part {
-        if !word.starts_with(prefix) {
-            continue;
+
+    // Returns the length of the longest prefix of an impossible design that can still be
+    // arranged, i.e. the position where every attempt gets stuck. `None` if it is possible.
+    fn find_dead_end(&self, design: &str) -> Option<usize> {
+        let mut reachable: Vec<bool> = vec![false; design.len() + 1];
+        reachable[0] = true;
+        for start in 0..design.len() {
+            if reachable[start] {
+                for (_, end) in self.matches_at(design, start) {
+                    reachable[end] = true;
+                }
+            }
         }
-        if prefix.len() == word.len() {
-            counter += 1;
+        if reachable[design.len()] {
+            return None;
         }
-        counter += prefixes(part, word.strip_prefix(prefix).unwrap(), cache);
+        reachable.iter().rposition(|r| *r)
+    }
+
+    fn impossible_designs<'d>(&self, designs: &[&'d str]) -> Vec<(&'d str, usize)> {
+        designs
+            .iter()
+            .filter_map(|design| self.find_dead_end(design).map(|stuck| (*design, stuck)))
+            .collect()
+    }
+}
+
+// Depth first search over the positions of a design. Positions from which the rest can't be
+// arranged are never entered, so every step leads to an arrangement.
+struct Arrangements<'a, 'd> {
+    trie: &'d TowelTrie<'a>,
+    design: &'d str,
+    suffix_arrangements: Vec<u64>,
+    stack: Vec<(usize, Vec<usize>)>,
+}
+
+impl<'a> Iterator for Arrangements<'a, '_> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((position, towels)) = self.stack.pop() {
+            if self.suffix_arrangements[position] == 0 {
+                continue;
+            }
+            if position == self.design.len() {
+                return Some(towels.iter().map(|t| self.trie.towels[*t]).collect());
+            }
+            let matches = self
+                .trie
+                .matches_at(self.design, position)
+                .collect::<Vec<_>>();
+            for (towel, end) in matches.into_iter().rev() {
+                let mut next_towels = towels.clone();
+                next_towels.push(towel);
+                self.stack.push((end, next_towels));
+            }
+        }
+        None
     }
-    cache.insert(word, counter);
-    counter
 }
 
 #[cfg(test)]
@@ -54,7 +214,7 @@ mod tests {
     #[test]
     fn test_parse_puzzle() {
         let input = std::fs::read_to_string("./resources/day19/example.txt").unwrap();
-        let (towels, target) = parse_puzzle(&*input);
+        let (towels, target) = parse_puzzle(&input);
         assert_eq!(towels.len(), 8);
         assert_eq!(target.len(), 8);
     }
@@ -62,7 +222,7 @@ mod tests {
     #[test]
     fn test_part_one() {
         let input = std::fs::read_to_string("./resources/day19/input.txt").unwrap();
-        let (towels, target) = parse_puzzle(&*input);
+        let (towels, target) = parse_puzzle(&input);
         let solution = solve_part_one(&towels, &target);
         println!("{}", solution);
     }
@@ -70,8 +230,35 @@ mod tests {
     #[test]
     fn test_part_two() {
         let input = std::fs::read_to_string("./resources/day19/input.txt").unwrap();
-        let (towels, target) = parse_puzzle(&*input);
+        let (towels, target) = parse_puzzle(&input);
         let solution = solve_part_two(&towels, &target);
         println!("{}", solution);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_towel_trie() {
+        let input =
+            "r, wr, b, g, bwu, rb, gb, br\n\nbrwrr\nbggr\ngbbr\nrrbgbr\nubwu\nbwurrg\nbrgr\nbbrwb";
+        let (towels, designs) = parse_puzzle(input);
+        assert_eq!(solve_part_one(&towels, &designs), 6);
+        assert_eq!(solve_part_two(&towels, &designs), 16);
+
+        let trie = TowelTrie::new(&towels);
+        let arrangements = trie.arrangements("brwrr").collect::<Vec<_>>();
+        assert_eq!(
+            arrangements,
+            vec![vec!["b", "r", "wr", "r"], vec!["br", "wr", "r"]]
+        );
+        assert_eq!(trie.arrangements("rrbgbr").count(), 6);
+        assert_eq!(trie.arrangements("ubwu").next(), None);
+        assert_eq!(
+            trie.arrangements("").collect::<Vec<_>>(),
+            vec![Vec::<&str>::new()]
+        );
+
+        assert_eq!(
+            trie.impossible_designs(&designs),
+            vec![("ubwu", 0), ("bbrwb", 3)]
+        );
+    }
+}
@@ -1,6 +1,5 @@
 use std::collections::{HashSet, VecDeque};
 use std::fmt::Display;
-use std::iter::{repeat, zip};
 
 pub fn solve(input_file_path: &str) -> (String, String) {
     let input = std::fs::read_to_string(input_file_path).unwrap();
@@ -11,21 +10,37 @@ pub fn solve(input_file_path: &str) -> (String, String) {
 }
 
 fn solve_part_one(puzzle: &Maze) -> String {
-    puzzle
-        .get_shortcuts(2)
-        .iter()
-        .filter(|(_, c, _)| *c >= 100)
-        .count()
-        .to_string()
+    puzzle.count_cheats(2, 100).to_string()
 }
 
 fn solve_part_two(puzzle: &Maze) -> String {
-    puzzle
-        .get_shortcuts(20)
-        .iter()
-        .filter(|(_, c, _)| *c >= 100)
-        .count()
-        .to_string()
+    puzzle.count_cheats(20, 100).to_string()
+}
+
+// Prints how many cheats save how much time, like the listing in the puzzle description
+pub fn print_cheat_histogram(input_file_path: &str, cheat_length: usize, min_saving: Costs) {
+    let input = std::fs::read_to_string(input_file_path).unwrap();
+    let puzzle = parse(&input);
+    let histogram = puzzle.get_cheat_histogram(cheat_length);
+    println!("{}", format_cheat_histogram(&histogram, min_saving));
+}
+
+fn format_cheat_histogram(histogram: &[usize], min_saving: Costs) -> String {
+    let mut lines: Vec<String> = vec![];
+    for (saving, count) in histogram.iter().enumerate().skip(min_saving.max(1)) {
+        match count {
+            0 => {}
+            1 => lines.push(format!(
+                "There is one cheat that saves {} picoseconds.",
+                saving
+            )),
+            _ => lines.push(format!(
+                "There are {} cheats that save {} picoseconds.",
+                count, saving
+            )),
+        }
+    }
+    lines.join("\n")
 }
 
 struct Maze {
@@ -83,81 +98,47 @@ impl Maze {
         Ok(costs_to_target)
     }
 
-    fn get_shortcuts(&self, steps: u32) -> Vec<(Position, Costs, Position)> {
+    fn count_cheats(&self, cheat_length: usize, min_saving: Costs) -> usize {
+        let histogram = self.get_cheat_histogram(cheat_length);
+        histogram.iter().skip(min_saving.max(1)).sum()
+    }
+
+    // Number of cheats per saving, the index being the saved picoseconds. Every cell of the
+    // track is paired with the cells within the diamond of the cheat length around it.
+    fn get_cheat_histogram(&self, cheat_length: usize) -> Vec<usize> {
         let costs_to_end = self.get_costs_to_position(&self.get_end_pos()).unwrap();
         let costs_to_start = self.get_costs_to_position(&self.get_start_pos()).unwrap();
 
         let start_pos = self.get_start_pos();
         let solution_without_shortcuts = costs_to_end[start_pos.1 * self.width + start_pos.0];
-
-        let mut cheats: Vec<(Position, Costs, Position)> =
-            Vec::with_capacity((steps * steps * 4) as usize);
-
-        for y in 1..self.height - 1 {
-            for x in 1..self.width - 1 {
-                let shortcut_end_pos = self.get_cheat_end_positions((x, y), steps);
-                let shortcuts = zip(repeat((x, y)), shortcut_end_pos);
-
-                for (a, b) in shortcuts {
-                    if !self.is_position_free(a) || !self.is_position_free(b) {
-                        continue;
-                    }
-
-                    let cost_a_to_start = costs_to_start[a.1 * self.width + a.0];
-                    let cost_b_to_end = costs_to_end[b.1 * self.width + b.0];
-
-                    let cheat_costs = a.0.abs_diff(b.0) + a.1.abs_diff(b.1);
-
-                    let discounted_path_costs =
-                        cost_a_to_start + cost_b_to_end + cheat_costs as Costs;
-                    if discounted_path_costs >= solution_without_shortcuts {
-                        continue;
-                    }
-                    let discount = solution_without_shortcuts - discounted_path_costs;
-
-                    cheats.push((a, discount, b));
-                }
-            }
+        if solution_without_shortcuts == Costs::MAX {
+            return vec![];
         }
-        cheats
-    }
 
-    fn get_cheat_end_positions(&self, (sx, sy): Position, steps: u32) -> HashSet<Position> {
-        let mut cheat_end_positions: HashSet<Position> = HashSet::new();
-        let sx = sx as i32;
-        let sy = sy as i32;
-        let steps = steps as i32;
+        let offsets = get_diamond_offsets(cheat_length);
+        let mut histogram: Vec<usize> = vec![0; solution_without_shortcuts + 1];
 
-        for y in 0..steps + 1 {
-            for x in 0..steps + 1 {
-                if x + y > steps || (x == 0 && y == 0) {
+        for (index, &cost_a_to_start) in costs_to_start.iter().enumerate() {
+            if cost_a_to_start >= solution_without_shortcuts {
+                continue;
+            }
+            let (x, y) = ((index % self.width) as i32, (index / self.width) as i32);
+            for &(dx, dy, cheat_costs) in &offsets {
+                let (bx, by) = (x + dx, y + dy);
+                if bx < 0 || by < 0 || bx >= self.width as i32 || by >= self.height as i32 {
                     continue;
                 }
-                let step_end_pos = vec![
-                    (sx + x, sy + y),
-                    (sx + x, sy - y),
-                    (sx - x, sy + y),
-                    (sx - x, sy - y),
-                ];
-                step_end_pos
-                    .iter()
-                    .filter(|(px, py)| {
-                        px >= &0
-                            && py >= &0
-                            && px < &(self.width as i32)
-                            && py < &(self.height as i32)
-                    })
-                    .filter(|(px, py)| {
-                        let p = (*px as usize, *py as usize);
-                        self.is_position_free(p)
-                    })
-                    .map(|(px, py)| (*px as usize, *py as usize))
-                    .for_each(|(px, py)| {
-                        cheat_end_positions.insert((px, py));
-                    });
+                let cost_b_to_end = costs_to_end[by as usize * self.width + bx as usize];
+                if cost_b_to_end == Costs::MAX {
+                    continue;
+                }
+                let discounted_path_costs = cost_a_to_start + cheat_costs + cost_b_to_end;
+                if discounted_path_costs < solution_without_shortcuts {
+                    histogram[solution_without_shortcuts - discounted_path_costs] += 1;
+                }
             }
         }
-        cheat_end_positions
+        histogram
     }
 
     fn is_position_free(&self, (x, y): (usize, usize)) -> bool {
@@ -165,6 +146,21 @@ impl Maze {
     }
 }
 
+// All offsets with a manhattan distance in 1..=length together with their distance
+fn get_diamond_offsets(length: usize) -> Vec<(i32, i32, Costs)> {
+    let length = length as i32;
+    let mut offsets: Vec<(i32, i32, Costs)> = vec![];
+    for dy in -length..=length {
+        let remaining = length - dy.abs();
+        for dx in -remaining..=remaining {
+            if dx != 0 || dy != 0 {
+                offsets.push((dx, dy, (dx.abs() + dy.abs()) as Costs));
+            }
+        }
+    }
+    offsets
+}
+
 impl Display for Maze {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         writeln!(f, "Width: {} , Height: {}", self.width, self.height,)
@@ -198,4 +194,40 @@ mod tests {
         assert_eq!(puzzle.width, 15);
         assert_eq!(puzzle.height, 15);
     }
+
+    const EXAMPLE: &str = "###############\n#...#...#.....#\n#.#.#.#.#.###.#\n#S#...#.#.#...#\n#######.#.#.###\n#######.#.#...#\n#######.#.###.#\n###..E#...#...#\n###.#######.###\n#...###...#...#\n#.#####.#.###.#\n#.#...#.#.#...#\n#.#.#.#.#.#.###\n#...#...#...###\n###############";
+
+    #[test]
+    fn test_cheat_histogram() {
+        let puzzle = parse(EXAMPLE);
+        let histogram = puzzle.get_cheat_histogram(2);
+        let expected = [
+            (2, 14),
+            (4, 14),
+            (6, 2),
+            (8, 4),
+            (10, 2),
+            (12, 3),
+            (20, 1),
+            (36, 1),
+            (38, 1),
+            (40, 1),
+            (64, 1),
+        ];
+        assert_eq!(histogram.iter().sum::<usize>(), 44);
+        for (saving, count) in expected {
+            assert_eq!(histogram[saving], count);
+        }
+        assert_eq!(
+            format_cheat_histogram(&histogram, 30),
+            "There is one cheat that saves 36 picoseconds.\n\
+             There is one cheat that saves 38 picoseconds.\n\
+             There is one cheat that saves 40 picoseconds.\n\
+             There is one cheat that saves 64 picoseconds."
+        );
+
+        assert_eq!(puzzle.count_cheats(20, 50), 285);
+        assert_eq!(puzzle.count_cheats(20, 76), 3);
+        assert_eq!(puzzle.get_cheat_histogram(20)[74], 4);
+    }
 }
@@ -1,9 +1,10 @@
-use crate::solutions::day21::AbstractKey::{Accept, ArrowBottom, ArrowRight, ArrowLeft, ArrowTop, Number};
+use crate::solutions::day21::AbstractKey::{
+    Accept, ArrowBottom, ArrowLeft, ArrowRight, ArrowTop, Number, Symbol,
+};
 use std::cmp::min;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 pub fn solve(input_file_path: &str) -> (String, String) {
-
     let input = std::fs::read_to_string(input_file_path).unwrap();
     let sequences = parse_input(&input);
     let part_one = calc_costs_for_seq(&sequences, 2);
@@ -12,6 +13,39 @@ pub fn solve(input_file_path: &str) -> (String, String) {
     (part_one.to_string(), part_two.to_string())
 }
 
+// Longest human sequence the explanation prints
+const MAX_LISTED_SEQUENCE: Costs = 200;
+
+// Lists the buttons the human has to press for every code with the chain of part one. Each
+// sequence is replayed through the robots to show that it enters the code.
+pub fn explain(input_file_path: &str) -> Result<String, String> {
+    let input = std::fs::read_to_string(input_file_path).map_err(|e| e.to_string())?;
+    let num_pad = AbstractKeyPad::new_numpad();
+    let dir_pad = AbstractKeyPad::new_direction_pad();
+    let pad_chain = create_pad_chain(&num_pad, &[&dir_pad, &dir_pad])?;
+
+    let mut lines: Vec<String> = vec![];
+    for (code, _) in parse_input(&input) {
+        let code_string = code.iter().map(|k| k.to_char()).collect::<String>();
+        match calc_human_sequence(&code, &pad_chain, MAX_LISTED_SEQUENCE) {
+            Ok(sequence) => {
+                let entered = replay_sequence(&sequence, &pad_chain)?;
+                let sequence_string = sequence.iter().map(|k| k.to_char()).collect::<String>();
+                let check = if entered == code { "ok" } else { "mismatch" };
+                lines.push(format!(
+                    "{}: {} ({} presses, replay {})",
+                    code_string,
+                    sequence_string,
+                    sequence.len(),
+                    check
+                ));
+            }
+            Err(length) => lines.push(format!("{}: {} presses", code_string, length)),
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
 type Key = AbstractKey;
 type Position = (i8, i8);
 type Costs = u64;
@@ -25,8 +59,50 @@ enum AbstractKey {
     ArrowTop,
     ArrowBottom,
     ArrowLeft,
+    Symbol(char),
+}
+
+impl AbstractKey {
+    fn from_char(c: char) -> AbstractKey {
+        match c {
+            'A' => Accept,
+            '^' => ArrowTop,
+            'v' => ArrowBottom,
+            '<' => ArrowLeft,
+            '>' => ArrowRight,
+            x if x.is_ascii_digit() => Number(x.to_digit(10).unwrap() as u8),
+            x => Symbol(x),
+        }
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            Accept => 'A',
+            ArrowTop => '^',
+            ArrowBottom => 'v',
+            ArrowLeft => '<',
+            ArrowRight => '>',
+            Number(n) => char::from_digit(n as u32, 10).unwrap(),
+            Symbol(c) => c,
+        }
+    }
+
+    fn direction(&self) -> Option<Position> {
+        match self {
+            ArrowTop => Some((0, 1)),
+            ArrowBottom => Some((0, -1)),
+            ArrowLeft => Some((-1, 0)),
+            ArrowRight => Some((1, 0)),
+            _ => None,
+        }
+    }
 }
 
+const DIRECTIONS: [Key; 4] = [ArrowLeft, ArrowBottom, ArrowTop, ArrowRight];
+
+const NUMPAD_LAYOUT: &str = "789\n456\n123\n 0A";
+const DIRECTION_PAD_LAYOUT: &str = " ^A\n<v>";
+
 struct AbstractKeyPad {
     keys: Vec<(Position, Key)>,
 }
@@ -36,63 +112,108 @@ impl AbstractKeyPad {
         AbstractKeyPad { keys }
     }
 
-    #[rustfmt::skip]
+    // Parses a keypad from its textual layout. Rows are read top to bottom and spaces mark gaps
+    // the robot arm must never point at, e.g. " ^A\n<v>" for the directional keypad.
+    fn from_layout(layout: &str) -> Result<AbstractKeyPad, String> {
+        let rows = layout.lines().collect::<Vec<&str>>();
+        let mut keys: Vec<(Position, Key)> = vec![];
+        for (row_index, row) in rows.iter().enumerate() {
+            let y = (rows.len() - 1 - row_index) as i8;
+            for (x, c) in row.chars().enumerate() {
+                if c == ' ' {
+                    continue;
+                }
+                let key = AbstractKey::from_char(c);
+                if keys.iter().any(|&(_, k)| k == key) {
+                    return Err(format!("Key '{}' appears more than once", c));
+                }
+                keys.push(((x as i8, y), key));
+            }
+        }
+        if !keys.iter().any(|&(_, k)| k == Accept) {
+            return Err("Keypad has no 'A' key".to_string());
+        }
+        let pad = AbstractKeyPad::new(keys);
+        if !pad.is_connected() {
+            return Err("Keypad has keys that can't be reached from the others".to_string());
+        }
+        Ok(pad)
+    }
+
+    // Every key has to be reachable from every other one without pointing at a gap
+    fn is_connected(&self) -> bool {
+        let Some(&(start, _)) = self.keys.first() else {
+            return true;
+        };
+        let mut reached: Vec<Position> = vec![start];
+        let mut queue: VecDeque<Position> = VecDeque::from([start]);
+        while let Some(pos) = queue.pop_front() {
+            for direction in DIRECTIONS.iter().filter_map(|d| d.direction()) {
+                let next = (pos.0 + direction.0, pos.1 + direction.1);
+                if self.get_key_at_pos(&next).is_some() && !reached.contains(&next) {
+                    reached.push(next);
+                    queue.push_back(next);
+                }
+            }
+        }
+        reached.len() == self.keys.len()
+    }
+
     fn new_numpad() -> AbstractKeyPad {
-        let pad = AbstractKeyPad::new(vec![
-            ((0, 3), Number(7)),((1, 3), Number(8)),((2, 3), Number(9)),
-            ((0, 2), Number(4)),((1, 2), Number(5)),((2, 2), Number(6)),
-            ((0, 1), Number(1)),((1, 1), Number(2)),((2, 1), Number(3)),
-                                ((1, 0), Number(0)),((2, 0), Accept),
-        ]);
-        pad
+        AbstractKeyPad::from_layout(NUMPAD_LAYOUT).unwrap()
     }
 
-    #[rustfmt::skip]
     fn new_direction_pad() -> AbstractKeyPad {
-        let pad = AbstractKeyPad::new(vec![
-                                ((1, 1), ArrowTop),   ((2, 1), Accept),
-            ((0, 0), ArrowLeft),((1, 0), ArrowBottom),((2, 0), ArrowRight),
-        ]);
-        pad
+        AbstractKeyPad::from_layout(DIRECTION_PAD_LAYOUT).unwrap()
     }
 
+    fn is_direction_pad(&self) -> bool {
+        DIRECTIONS
+            .iter()
+            .chain([Accept].iter())
+            .all(|key| self.get_position_of_key(key).is_some())
+    }
+
+    // All shortest routes of the robot arm from `start_key` to `key_to_press`, each followed by
+    // pressing 'A'. Routes are found with a breadth first search, so gaps of any shape are avoided.
     fn get_sequences_to_enter_key(&self, key_to_press: &Key, start_key: &Key) -> Vec<Vec<Action>> {
         let start_pos = self.get_position_of_key(start_key).unwrap();
         let end_pos = self.get_position_of_key(key_to_press).unwrap();
 
-        let (dx, dy) = (end_pos.0 - start_pos.0, end_pos.1 - start_pos.1);
-
-        let horizontal = if dx > 0 {
-            vec![ArrowRight; dx.abs() as usize]
-        } else {
-            vec![ArrowLeft; dx.abs() as usize]
-        };
-
-        let vertical = if dy < 0 {
-            vec![ArrowBottom; dy.abs() as usize]
-        } else {
-            vec![ArrowTop; dy.abs() as usize]
-        };
-
-        let can_move_horizontal = self.get_key_at_pos(&(end_pos.0, start_pos.1)).is_some();
-        let can_move_vertical = self.get_key_at_pos(&(start_pos.0, end_pos.1)).is_some();
-
-        let mut output: Vec<Vec<Action>> = Vec::new();
-
-        if can_move_horizontal {
-            let mut pos_seq_1 = horizontal.clone();
-            pos_seq_1.append(&mut vertical.clone());
-            pos_seq_1.push(Accept);
-            output.push(pos_seq_1);
+        let mut distances: HashMap<Position, usize> = HashMap::from([(end_pos, 0)]);
+        let mut queue: VecDeque<Position> = VecDeque::from([end_pos]);
+        while let Some(pos) = queue.pop_front() {
+            for direction in DIRECTIONS.iter().filter_map(|d| d.direction()) {
+                let next = (pos.0 + direction.0, pos.1 + direction.1);
+                if self.get_key_at_pos(&next).is_some() && !distances.contains_key(&next) {
+                    distances.insert(next, distances[&pos] + 1);
+                    queue.push_back(next);
+                }
+            }
         }
 
-        if can_move_vertical {
-            let mut pos_seq_2 = vertical.clone();
-            pos_seq_2.append(&mut horizontal.clone());
-            pos_seq_2.push(Accept);
-            output.push(pos_seq_2);
+        let mut output: Vec<Vec<Action>> = Vec::new();
+        let mut stack: Vec<(Position, Vec<Action>)> = vec![(start_pos, vec![])];
+        while let Some((pos, mut sequence)) = stack.pop() {
+            if pos == end_pos {
+                sequence.push(Accept);
+                output.push(sequence);
+                continue;
+            }
+            for key in DIRECTIONS {
+                let direction = key.direction().unwrap();
+                let next = (pos.0 + direction.0, pos.1 + direction.1);
+                if distances
+                    .get(&next)
+                    .is_some_and(|&d| d + 1 == distances[&pos])
+                {
+                    let mut next_sequence = sequence.clone();
+                    next_sequence.push(key);
+                    stack.push((next, next_sequence));
+                }
+            }
         }
-
+        output.sort();
         output
     }
 
@@ -106,7 +227,7 @@ impl AbstractKeyPad {
 }
 
 fn get_costs_recursive(
-    keypads: &Vec<&AbstractKeyPad>,
+    keypads: &[&AbstractKeyPad],
     seq: Vec<Key>,
     depth: u8,
     cache: &mut HashMap<(Vec<Key>, u8), Costs>,
@@ -120,8 +241,8 @@ fn get_costs_recursive(
     if depth == (keypads.len() - 1) as u8 {
         let mut current_key = Accept;
         for key in seq.iter() {
-            let c = pad.get_sequences_to_enter_key(&key, &current_key);
-            total_sequence_costs += c.get(0).unwrap().len() as Costs;
+            let c = pad.get_sequences_to_enter_key(key, &current_key);
+            total_sequence_costs += c.first().unwrap().len() as Costs;
             current_key = *key
         }
     } else {
@@ -141,15 +262,43 @@ fn get_costs_recursive(
     total_sequence_costs
 }
 
-fn parse_input(input: &str) -> Vec<(Vec<Key>,u64)> {
+// Expands `seq` into the buttons the human has to press, always following the cheapest
+// sub-sequence. The result has exactly the length reported by `get_costs_recursive`.
+fn get_sequence_recursive(
+    keypads: &[&AbstractKeyPad],
+    seq: &[Key],
+    depth: u8,
+    cache: &mut HashMap<(Vec<Key>, u8), Costs>,
+) -> Vec<Key> {
+    let pad = keypads[depth as usize];
+    let mut output: Vec<Key> = vec![];
+    let mut current_key = Accept;
+    for key in seq.iter() {
+        let candidates = pad.get_sequences_to_enter_key(key, &current_key);
+        if depth == (keypads.len() - 1) as u8 {
+            output.extend(candidates.first().unwrap());
+        } else {
+            let cheapest = candidates
+                .into_iter()
+                .min_by_key(|c| get_costs_recursive(keypads, c.clone(), depth + 1, cache))
+                .unwrap();
+            output.extend(get_sequence_recursive(keypads, &cheapest, depth + 1, cache));
+        }
+        current_key = *key
+    }
+    output
+}
+
+fn parse_input(input: &str) -> Vec<(Vec<Key>, u64)> {
     let keys = input
         .lines()
         .map(|line| {
-            let key_presses = line.chars()
+            let key_presses = line
+                .chars()
                 .filter_map(|c| match c {
                     'A' => Some(Accept),
                     x => {
-                        if x.is_digit(10) {
+                        if x.is_ascii_digit() {
                             Some(Number(x.to_digit(10).unwrap() as u8))
                         } else {
                             None
@@ -164,22 +313,97 @@ fn parse_input(input: &str) -> Vec<(Vec<Key>,u64)> {
     keys
 }
 
-fn calc_costs_for_seq(sequences: &Vec<(Vec<Key>, u64)>, dir_pads: u8) -> Costs{
+// A target keypad followed by the directional keypads used to operate it. Each keypad is
+// pressed by a robot, the robot at the last keypad is controlled by the human.
+fn create_pad_chain<'a>(
+    target_pad: &'a AbstractKeyPad,
+    dir_pads: &[&'a AbstractKeyPad],
+) -> Result<Vec<&'a AbstractKeyPad>, String> {
+    if let Some(i) = dir_pads.iter().position(|pad| !pad.is_direction_pad()) {
+        return Err(format!(
+            "Keypad {} of the chain lacks directional keys",
+            i + 1
+        ));
+    }
+    let mut pad_chain: Vec<&AbstractKeyPad> = vec![target_pad];
+    pad_chain.extend(dir_pads);
+    Ok(pad_chain)
+}
+
+fn calc_costs_for_seq(sequences: &[(Vec<Key>, u64)], dir_pads: u8) -> Costs {
     let num_pad = AbstractKeyPad::new_numpad();
     let dir_pad = AbstractKeyPad::new_direction_pad();
+    let pad_chain = create_pad_chain(&num_pad, &vec![&dir_pad; dir_pads as usize]).unwrap();
+    calc_costs_for_chain(sequences, &pad_chain)
+}
 
-    let mut pad_chain: Vec<&AbstractKeyPad> = vec![];
-    pad_chain.append(&mut vec![&num_pad]);
-    pad_chain.append(&mut vec![&dir_pad; dir_pads as usize]);
-
+fn calc_costs_for_chain(sequences: &[(Vec<Key>, u64)], pad_chain: &[&AbstractKeyPad]) -> Costs {
     let mut total_cost = 0;
     let mut cache: HashMap<(Vec<Key>, u8), Costs> = HashMap::new();
     for (sequence, number) in sequences {
-        let c = get_costs_recursive(&pad_chain, sequence.clone(), 0, &mut cache);
+        let c = get_costs_recursive(pad_chain, sequence.clone(), 0, &mut cache);
         total_cost += c * number;
     }
     total_cost
+}
 
+// Returns an optimal sequence of human button presses entering `sequence` on the first pad of
+// the chain. The length grows exponentially with the chain, so sequences longer than
+// `max_length` are refused with their length as error.
+fn calc_human_sequence(
+    sequence: &[Key],
+    pad_chain: &[&AbstractKeyPad],
+    max_length: Costs,
+) -> Result<Vec<Key>, Costs> {
+    let mut cache: HashMap<(Vec<Key>, u8), Costs> = HashMap::new();
+    let costs = get_costs_recursive(pad_chain, sequence.to_vec(), 0, &mut cache);
+    if costs > max_length {
+        return Err(costs);
+    }
+    Ok(get_sequence_recursive(pad_chain, sequence, 0, &mut cache))
+}
+
+// Replays human button presses through the robot chain and returns the keys pressed on the
+// first pad. Fails if a robot arm points at a gap or a robot receives a non-directional key.
+fn replay_sequence(
+    human_sequence: &[Key],
+    pad_chain: &[&AbstractKeyPad],
+) -> Result<Vec<Key>, String> {
+    let mut arms: Vec<Position> = pad_chain
+        .iter()
+        .map(|pad| pad.get_position_of_key(&Accept).unwrap())
+        .collect();
+    let mut output: Vec<Key> = vec![];
+
+    for (step, human_key) in human_sequence.iter().enumerate() {
+        let mut level = pad_chain.len();
+        let mut key = *human_key;
+        loop {
+            if level == 0 {
+                output.push(key);
+                break;
+            }
+            let robot_pad = pad_chain[level - 1];
+            if key == Accept {
+                key = robot_pad.get_key_at_pos(&arms[level - 1]).unwrap();
+                level -= 1;
+                continue;
+            }
+            let direction = key.direction().ok_or(format!(
+                "Step {}: robot {} can't handle '{}'",
+                step,
+                level,
+                key.to_char()
+            ))?;
+            let arm = &mut arms[level - 1];
+            *arm = (arm.0 + direction.0, arm.1 + direction.1);
+            if robot_pad.get_key_at_pos(arm).is_none() {
+                return Err(format!("Step {}: robot {} points at a gap", step, level));
+            }
+            break;
+        }
+    }
+    Ok(output)
 }
 
 #[cfg(test)]
@@ -187,10 +411,80 @@ mod tests {
     use super::*;
     #[test]
     fn test_part_one() {
-
         let input = std::fs::read_to_string("./resources/day21/example.txt").unwrap();
         let sequences = parse_input(&input);
         let part_one = calc_costs_for_seq(&sequences, 2);
         assert_eq!(part_one.to_string(), "126384")
     }
+
+    fn to_keys(sequence: &str) -> Vec<Key> {
+        sequence.chars().map(AbstractKey::from_char).collect()
+    }
+
+    fn to_string(sequence: &[Key]) -> String {
+        sequence.iter().map(|k| k.to_char()).collect()
+    }
+
+    #[test]
+    fn test_layouts() {
+        let num_pad = AbstractKeyPad::new_numpad();
+        assert_eq!(num_pad.get_position_of_key(&Number(7)), Some((0, 3)));
+        assert_eq!(num_pad.get_position_of_key(&Number(0)), Some((1, 0)));
+        assert_eq!(num_pad.get_key_at_pos(&(0, 0)), None);
+        assert!(!num_pad.is_direction_pad());
+
+        let dir_pad = AbstractKeyPad::new_direction_pad();
+        assert_eq!(dir_pad.get_position_of_key(&Accept), Some((2, 1)));
+        assert!(dir_pad.is_direction_pad());
+
+        assert!(AbstractKeyPad::from_layout("1A\n1 ").is_err());
+        assert!(AbstractKeyPad::from_layout("12\n34").is_err());
+        assert_eq!(
+            AbstractKeyPad::from_layout("1 2\n A ").err(),
+            Some("Keypad has keys that can't be reached from the others".to_string())
+        );
+        assert!(AbstractKeyPad::from_layout("1A\n 2").is_ok());
+        assert!(create_pad_chain(&dir_pad, &[&num_pad]).is_err());
+    }
+
+    #[test]
+    fn test_human_sequence() {
+        let num_pad = AbstractKeyPad::new_numpad();
+        let dir_pad = AbstractKeyPad::new_direction_pad();
+        let code = to_keys("029A");
+
+        let chain = create_pad_chain(&num_pad, &[]).unwrap();
+        let sequence = calc_human_sequence(&code, &chain, 100).unwrap();
+        assert_eq!(sequence.len(), 12);
+        assert_eq!(replay_sequence(&sequence, &chain), Ok(code.clone()));
+
+        let chain = create_pad_chain(&num_pad, &[&dir_pad, &dir_pad]).unwrap();
+        let sequence = calc_human_sequence(&code, &chain, 100).unwrap();
+        assert_eq!(sequence.len(), 68);
+        assert_eq!(replay_sequence(&sequence, &chain), Ok(code.clone()));
+        assert_eq!(calc_human_sequence(&code, &chain, 50), Err(68));
+
+        assert!(replay_sequence(&to_keys("^"), &chain).is_err());
+        assert!(replay_sequence(&to_keys("<A"), &chain).is_err());
+    }
+
+    #[test]
+    fn test_custom_layout() {
+        let pad = AbstractKeyPad::from_layout("1 2\n3A4").unwrap();
+        let dir_pad = AbstractKeyPad::new_direction_pad();
+        assert_eq!(
+            pad.get_sequences_to_enter_key(&Number(2), &Number(1)),
+            vec![to_keys("v>>^A")]
+        );
+
+        let chain = create_pad_chain(&pad, &[&dir_pad]).unwrap();
+        let code = to_keys("12A");
+        let sequence = calc_human_sequence(&code, &chain, 100).unwrap();
+        assert_eq!(replay_sequence(&sequence, &chain), Ok(code.clone()));
+        assert_eq!(
+            sequence.len() as Costs,
+            calc_costs_for_chain(&[(code, 1)], &chain)
+        );
+        assert!(to_string(&sequence).ends_with('A'));
+    }
 }
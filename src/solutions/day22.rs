@@ -1,5 +1,4 @@
-use std::collections::{HashMap, HashSet};
-use std::iter::zip;
+use std::collections::HashMap;
 
 pub fn solve(input_file_path: &str) -> (String, String) {
     let input = std::fs::read_to_string(input_file_path).unwrap();
@@ -16,119 +15,157 @@ pub fn solve(input_file_path: &str) -> (String, String) {
     (solution_one, solution_two)
 }
 
+// Follows the secret numbers of the first buyers forwards and backwards and names the pattern
+// to sell on
+pub fn explain(input_file_path: &str) -> Result<String, String> {
+    const LISTED_BUYERS: usize = 10;
+    let input = std::fs::read_to_string(input_file_path).map_err(|e| e.to_string())?;
+    let initial_secrets = input
+        .lines()
+        .map(|line| {
+            line.parse::<u64>()
+                .map_err(|_| format!("Invalid secret {}", line))
+        })
+        .collect::<Result<Vec<u64>, String>>()?;
+
+    let mut lines: Vec<String> = vec![];
+    for secret in initial_secrets.iter().take(LISTED_BUYERS) {
+        lines.push(format!(
+            "{}: {} after {} steps, {} before, repeats after {} steps",
+            secret,
+            jump_ahead(*secret, PRICE_CHANGES as u64),
+            PRICE_CHANGES,
+            previous_secret_number(*secret),
+            cycle_length(*secret)
+        ));
+    }
+    if initial_secrets.len() > LISTED_BUYERS {
+        lines.push("...".to_string());
+    }
+    let (pattern, revenue) = MonkeyMarket::new(&initial_secrets).find_best_pattern(PRICE_CHANGES);
+    lines.push(format!(
+        "Selling on the changes {:?} earns {} bananas",
+        pattern, revenue
+    ));
+    Ok(lines.join("\n"))
+}
+
 fn solve_part_one(monkey_market: &MonkeyMarket) -> u64 {
-    monkey_market.get_secret_numbers_at(2000).iter().sum()
+    monkey_market
+        .get_secret_numbers_at(PRICE_CHANGES)
+        .iter()
+        .sum()
 }
 
 fn solve_part_two(monkey_market: &MonkeyMarket) -> u64 {
-    let patterns = monkey_market.generate_all_patterns(4);
-    let cache = monkey_market.create_dictionaries();
-
-    let mut best_revenue: u32 = 0;
-
-    for pattern in patterns {
-        // Test Pattern
-        let revenue_of_pattern = cache
-            .iter()
-            .filter_map(|dic| dic.get(&pattern))
-            .map(|x| *x as u32)
-            .sum::<u32>();
-
-        if revenue_of_pattern > best_revenue {
-            best_revenue = revenue_of_pattern;
-        }
-    }
+    let (_, best_revenue) = monkey_market.find_best_pattern(PRICE_CHANGES);
     best_revenue as u64
 }
 
 type Pattern = (i8, i8, i8, i8);
+
+const PRICE_CHANGES: usize = 2000;
+const PATTERN_LENGTH: usize = 4;
+// Price changes range from -9 to 9
+const DIFF_RANGE: usize = 19;
+const PATTERN_COUNT: usize = DIFF_RANGE * DIFF_RANGE * DIFF_RANGE * DIFF_RANGE;
+
 struct MonkeyMarket {
     initial_secret: Vec<u64>,
-    prices: Vec<Vec<u8>>,
-    diffs: Vec<Vec<i8>>,
 }
 
 impl MonkeyMarket {
-    fn new(initial_secrets: &Vec<u64>) -> MonkeyMarket {
-        let mut prices: Vec<Vec<u8>> = Vec::with_capacity(initial_secrets.len());
-        let mut diffs: Vec<Vec<i8>> = Vec::with_capacity(initial_secrets.len());
-
-        for s in initial_secrets {
-            let steps = 2000;
-            let mut price: Vec<u8> = Vec::with_capacity(steps);
-
-            let mut current = *s;
-            for _ in 0..steps {
-                price.push((current % 10) as u8);
-                let next_secret = next_secret_number(current);
-                current = next_secret;
-            }
-
-            let local_diffs = get_price_diffs(&price);
-
-            prices.push(price);
-            diffs.push(local_diffs)
-        }
-
+    fn new(initial_secrets: &[u64]) -> MonkeyMarket {
         MonkeyMarket {
-            initial_secret: initial_secrets.clone(),
-            prices,
-            diffs,
+            initial_secret: initial_secrets.to_vec(),
         }
     }
 
     fn get_secret_numbers_at(&self, steps: usize) -> Vec<u64> {
+        let jump = SecretMatrix::step().pow(steps as u64);
         self.initial_secret
             .iter()
-            .map(|initial_secret| {
-                let mut current = *initial_secret;
-                for _ in 0..steps {
-                    let next_secret = next_secret_number(current);
-                    current = next_secret;
-                }
-                current
-            })
+            .map(|initial_secret| jump.apply(*initial_secret))
             .collect::<Vec<u64>>()
     }
 
-    fn generate_all_patterns(&self, pattern_length: i8) -> HashSet<Pattern> {
-        let mut unique_patterns: HashSet<Pattern> = HashSet::new();
+    // Sums up the price every pattern of four consecutive changes sells for, over all buyers,
+    // in a single pass. Patterns are encoded in base 19 and used as index into dense arrays.
+    fn score_patterns(&self, changes: usize) -> Vec<u32> {
+        let mut revenue: Vec<u32> = vec![0; PATTERN_COUNT];
+        // A monkey sells at the first occurrence of the pattern only
+        let mut last_seen_by: Vec<usize> = vec![usize::MAX; PATTERN_COUNT];
 
-        for monkey_index in 0..self.initial_secret.len() {
-            let diffs: &Vec<i8> = &self.diffs[monkey_index];
-            for i in 0..diffs.len() - pattern_length as usize {
-                let pattern = (diffs[i], diffs[i + 1], diffs[i + 2], diffs[i + 3]);
-                if !unique_patterns.contains(&pattern) {
-                    unique_patterns.insert(pattern);
+        for (buyer, initial_secret) in self.initial_secret.iter().enumerate() {
+            let mut pattern_index = 0;
+            for (step, tick) in secret_numbers(*initial_secret)
+                .take(changes + 1)
+                .enumerate()
+                .skip(1)
+            {
+                let diff = (tick.diff.unwrap() + 9) as usize;
+                pattern_index = (pattern_index * DIFF_RANGE + diff) % PATTERN_COUNT;
+                if step >= PATTERN_LENGTH && last_seen_by[pattern_index] != buyer {
+                    last_seen_by[pattern_index] = buyer;
+                    revenue[pattern_index] += tick.price as u32;
                 }
             }
         }
+        revenue
+    }
 
-        unique_patterns
+    fn find_best_pattern(&self, changes: usize) -> (Pattern, u32) {
+        let revenue = self.score_patterns(changes);
+        let (best_index, best_revenue) = revenue
+            .iter()
+            .enumerate()
+            .max_by_key(|&(index, revenue)| (*revenue, std::cmp::Reverse(index)))
+            .unwrap();
+        (decode_pattern(best_index), *best_revenue)
     }
+}
 
-    fn create_dictionaries(&self) -> Vec<HashMap<Pattern, u8>> {
-        let mut dictionaries: Vec<HashMap<Pattern, u8>> = Vec::new();
-        let pattern_length = 4;
+fn decode_pattern(pattern_index: usize) -> Pattern {
+    let diff = |position: u32| (pattern_index / DIFF_RANGE.pow(position) % DIFF_RANGE) as i8 - 9;
+    (diff(3), diff(2), diff(1), diff(0))
+}
 
-        for i in 0..self.initial_secret.len() {
-            let prices = &self.prices[i];
-            let diffs = &self.diffs[i];
-            let mut dic: HashMap<Pattern, u8> = HashMap::new();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketTick {
+    pub secret: u64,
+    pub price: u8,
+    // Change to the previous price, `None` for the initial secret
+    pub diff: Option<i8>,
+}
 
-            diffs
-                .windows(pattern_length as usize)
-                .enumerate()
-                .for_each(|(index, diffs)| {
-                    let prices = prices[index + pattern_length as usize];
-                    let e = (diffs[0], diffs[1], diffs[2], diffs[3]);
-                    if !dic.contains_key(&e) {
-                        dic.insert(e, prices);
-                    }
-                });
-            dictionaries.push(dic);
-        }
-        dictionaries
+// Endless stream of secret numbers of a buyer, starting with the initial secret itself
+pub struct SecretNumbers {
+    current: u64,
+    previous_price: Option<u8>,
+}
+
+impl Iterator for SecretNumbers {
+    type Item = MarketTick;
+
+    fn next(&mut self) -> Option<MarketTick> {
+        let secret = self.current;
+        let price = (secret % 10) as u8;
+        let diff = self.previous_price.map(|p| price as i8 - p as i8);
+
+        self.current = next_secret_number(secret);
+        self.previous_price = Some(price);
+        Some(MarketTick {
+            secret,
+            price,
+            diff,
+        })
+    }
+}
+
+pub fn secret_numbers(initial_secret: u64) -> SecretNumbers {
+    SecretNumbers {
+        current: initial_secret,
+        previous_price: None,
     }
 }
 
@@ -147,13 +184,128 @@ fn prune(secret: u64) -> u64 {
     secret % 16777216
 }
 
-fn get_price_diffs(prices: &Vec<u8>) -> Vec<i8> {
-    let iter_left = prices.iter();
-    let mut iter_right = prices.iter();
-    iter_right.next();
-    zip(iter_left, iter_right)
-        .map(|(x, y)| *y as i8 - *x as i8)
-        .collect()
+const SECRET_BITS: usize = 24;
+
+// `next_secret_number` only shifts, xors and truncates to 24 bits, so it's a linear map over
+// GF(2)^24. Column j of the matrix is the image of the secret with only bit j set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SecretMatrix {
+    columns: [u32; SECRET_BITS],
+}
+
+impl SecretMatrix {
+    fn identity() -> SecretMatrix {
+        let mut columns = [0; SECRET_BITS];
+        for (bit, column) in columns.iter_mut().enumerate() {
+            *column = 1 << bit;
+        }
+        SecretMatrix { columns }
+    }
+
+    fn step() -> SecretMatrix {
+        let mut columns = [0; SECRET_BITS];
+        for (bit, column) in columns.iter_mut().enumerate() {
+            *column = next_secret_number(1 << bit) as u32;
+        }
+        SecretMatrix { columns }
+    }
+
+    fn apply(&self, secret: u64) -> u64 {
+        let secret = prune(secret);
+        (0..SECRET_BITS)
+            .filter(|bit| secret >> bit & 1 == 1)
+            .fold(0, |acc, bit| acc ^ self.columns[bit]) as u64
+    }
+
+    // Matrix product, `other` is applied first
+    fn after(&self, other: &SecretMatrix) -> SecretMatrix {
+        let mut columns = [0; SECRET_BITS];
+        for (column, other_column) in columns.iter_mut().zip(other.columns.iter()) {
+            *column = self.apply(*other_column as u64) as u32;
+        }
+        SecretMatrix { columns }
+    }
+
+    fn pow(&self, mut exponent: u64) -> SecretMatrix {
+        let mut result = SecretMatrix::identity();
+        let mut base = *self;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = base.after(&result);
+            }
+            base = base.after(&base);
+            exponent >>= 1;
+        }
+        result
+    }
+
+    // Gauss-Jordan elimination on the augmented matrix (self | identity)
+    fn inverse(&self) -> Option<SecretMatrix> {
+        let mut rows: Vec<(u32, u32)> = (0..SECRET_BITS)
+            .map(|row| {
+                let left = (0..SECRET_BITS)
+                    .filter(|&column| self.columns[column] >> row & 1 == 1)
+                    .fold(0u32, |acc, column| acc | 1 << column);
+                (left, 1 << row)
+            })
+            .collect();
+
+        for pivot in 0..SECRET_BITS {
+            let pivot_row = (pivot..SECRET_BITS).find(|&row| rows[row].0 >> pivot & 1 == 1)?;
+            rows.swap(pivot, pivot_row);
+            let (pivot_left, pivot_right) = rows[pivot];
+            for (row, (left, right)) in rows.iter_mut().enumerate() {
+                if row != pivot && *left >> pivot & 1 == 1 {
+                    *left ^= pivot_left;
+                    *right ^= pivot_right;
+                }
+            }
+        }
+
+        let mut columns = [0; SECRET_BITS];
+        for (row, &(_, right)) in rows.iter().enumerate() {
+            for (column, value) in columns.iter_mut().enumerate() {
+                *value |= (right >> column & 1) << row;
+            }
+        }
+        Some(SecretMatrix { columns })
+    }
+}
+
+// Secret number after `steps` iterations in O(log steps)
+fn jump_ahead(secret: u64, steps: u64) -> u64 {
+    SecretMatrix::step().pow(steps).apply(secret)
+}
+
+fn previous_secret_number(secret: u64) -> u64 {
+    SecretMatrix::step().inverse().unwrap().apply(secret)
+}
+
+// Number of steps until `secret` repeats. Since the map is invertible every secret lies on a
+// cycle of at most 2^24 elements, which is found with baby-step giant-step in O(2^12).
+fn cycle_length(secret: u64) -> u64 {
+    let secret = prune(secret);
+    let stride: u64 = 1 << (SECRET_BITS / 2);
+
+    let mut baby_steps: HashMap<u64, u64> = HashMap::new();
+    let mut current = secret;
+    for j in 0..stride {
+        if j > 0 && current == secret {
+            return j;
+        }
+        baby_steps.insert(current, j);
+        current = next_secret_number(current);
+    }
+
+    let giant_step = SecretMatrix::step().pow(stride);
+    let mut current = secret;
+    for i in 1..=stride {
+        current = giant_step.apply(current);
+        if let Some(j) = baby_steps.get(&current) {
+            return i * stride - j;
+        }
+    }
+    unreachable!("Cycles are never longer than 2^24")
 }
 
 #[cfg(test)]
@@ -162,15 +314,80 @@ mod tests {
 
     #[test]
     fn test_part_one() {
-        let monkey_market = MonkeyMarket::new(&vec![1, 10, 100, 2024]);
+        let monkey_market = MonkeyMarket::new(&[1, 10, 100, 2024]);
         let solution = solve_part_one(&monkey_market);
         assert_eq!(solution, 37327623);
     }
 
     #[test]
     fn test_part_two() {
-        let monkey_market = MonkeyMarket::new(&vec![1, 2, 3, 2024]);
+        let monkey_market = MonkeyMarket::new(&[1, 2, 3, 2024]);
         let solution = solve_part_two(&monkey_market);
         assert_eq!(solution, 23);
+        assert_eq!(
+            monkey_market.find_best_pattern(PRICE_CHANGES),
+            ((-2, 1, -1, 3), 23)
+        );
+    }
+
+    #[test]
+    fn test_secret_numbers() {
+        let ticks = secret_numbers(123).take(10).collect::<Vec<MarketTick>>();
+        assert_eq!(ticks[1].secret, 15887950);
+        assert_eq!(ticks[9].secret, 7753432);
+        assert_eq!(
+            ticks.iter().map(|t| t.price).collect::<Vec<u8>>(),
+            vec![3, 0, 6, 5, 4, 4, 6, 4, 4, 2]
+        );
+        assert_eq!(
+            ticks.iter().map(|t| t.diff).collect::<Vec<Option<i8>>>(),
+            vec![
+                None,
+                Some(-3),
+                Some(6),
+                Some(-1),
+                Some(-1),
+                Some(0),
+                Some(2),
+                Some(-2),
+                Some(0),
+                Some(-2)
+            ]
+        );
+
+        // Pattern (-1, -1, 0, 2) first occurs at price 6
+        let index = ((8 * DIFF_RANGE + 8) * DIFF_RANGE + 9) * DIFF_RANGE + 11;
+        let revenue = MonkeyMarket::new(&[123]).score_patterns(9);
+        assert_eq!(revenue[index], 6);
+        assert_eq!(decode_pattern(index), (-1, -1, 0, 2));
+    }
+
+    #[test]
+    fn test_jump_ahead() {
+        for secret in [0, 1, 123, 2024, 16777215] {
+            let iterative = secret_numbers(secret)
+                .take(2001)
+                .map(|t| t.secret)
+                .collect::<Vec<u64>>();
+            for steps in [0, 1, 2, 3, 10, 255, 256, 1999, 2000] {
+                assert_eq!(jump_ahead(secret, steps as u64), iterative[steps]);
+            }
+            for next in iterative.windows(2) {
+                assert_eq!(previous_secret_number(next[1]), next[0]);
+            }
+        }
+        let step = SecretMatrix::step();
+        assert_eq!(
+            step.after(&step.inverse().unwrap()),
+            SecretMatrix::identity()
+        );
+    }
+
+    #[test]
+    fn test_cycle_length() {
+        assert_eq!(cycle_length(0), 1);
+        let length = cycle_length(123);
+        assert_eq!(jump_ahead(123, length), 123);
+        assert_eq!(length, 16777215);
     }
 }
@@ -1,4 +1,5 @@
 use std::collections::{HashMap, HashSet};
+use std::path::Path;
 
 pub fn solve(input_file_path: &str) -> (String, String) {
     let input = std::fs::read_to_string(input_file_path).unwrap();
@@ -6,22 +7,35 @@ pub fn solve(input_file_path: &str) -> (String, String) {
     (solve_part_one(&puzzle), solve_part_two(&puzzle))
 }
 
+// Writes the network with the LAN party highlighted as Graphviz and GraphML file
+pub fn explain(input_file_path: &str) -> Result<String, String> {
+    let input = std::fs::read_to_string(input_file_path).map_err(|e| e.to_string())?;
+    let graph = parse_input(&input);
+    let clique = find_maximum_clique(&graph);
+
+    let mut lines: Vec<String> = vec![format!(
+        "LAN party of {} computers: {}",
+        clique.len(),
+        clique.join(",")
+    )];
+    let files = [
+        ("lan.dot", export_dot(&graph, &clique)),
+        ("lan.graphml", export_graphml(&graph, &clique)),
+    ];
+    for (file_name, content) in files {
+        let path = Path::new(input_file_path).with_file_name(file_name);
+        std::fs::write(&path, content).map_err(|e| e.to_string())?;
+        lines.push(format!("Network written to {}", path.display()));
+    }
+    Ok(lines.join("\n"))
+}
+
 fn solve_part_one(graph: &Graph) -> String {
-    // A set `part_two_flag` disables the check, which ensures that at least one computer
-    // with prefix 't' is in the set. This also reduces the performance of the clique-check significantly
-    // Since the maximum clique in my puzzle contains a computer with prefix 't' I keep it disabled.
-    let res = find_k_cliques(graph, 3, false);
-    res.len().to_string()
+    count_k_cliques(graph, 3, &["t"]).to_string()
 }
 
 fn solve_part_two(graph: &Graph) -> String {
-    for clique_size in 1..graph.0.len() {
-        let clique_count = find_k_cliques(graph, clique_size, false);
-        if clique_count.len() == 1 {
-            return clique_count[0].join(",");
-        }
-    }
-    "No solution found".to_string()
+    find_maximum_clique(graph).join(",")
 }
 
 type Node = String;
@@ -55,124 +69,198 @@ fn parse_input(input: &str) -> Graph {
     (nodes, edges, neighbours)
 }
 
-fn find_k_cliques(graph: &Graph, k: usize, part_two_flag: bool) -> Vec<Vec<Node>> {
-    let possible_nodes = remove_recursively(&graph.2, (k - 1) as u32);
-
-    let mut result: HashSet<Vec<Node>> = HashSet::new();
-
-    possible_nodes
-        .iter()
-        .filter(|(n, _)| n.starts_with("t") || part_two_flag)
-        .for_each(|(s, n)| {
-            'combo: for combo_group in Permutes::new(Vec::from_iter(n.clone()), (k - 1) as i32) {
-                // Check if other nodes are connected to each other
-                for i in 0..combo_group.len() {
-                    for j in i + 1..combo_group.len() {
-                        let edge: Edge = (combo_group[i].clone(), combo_group[j].clone());
-                        let edge_rev: Edge = (edge.1.clone(), edge.0.clone());
-
-                        if !(graph.1.contains(&edge) || graph.1.contains(&edge_rev)) {
-                            continue 'combo;
-                        }
-                    }
-                }
-                // Combo group is valid
-                let mut connected_group: Vec<Node> = Vec::new();
-                connected_group.push(s.clone());
-                connected_group.append(&mut combo_group.clone());
-                connected_group.sort();
-                result.insert(connected_group);
+// Counts the cliques of exactly k computers with at least one name starting with one of the
+// prefixes, every clique is grown from its smallest member by adding only larger neighbours
+fn count_k_cliques(graph: &Graph, k: usize, prefixes: &[&str]) -> usize {
+    fn extend(
+        graph: &Graph,
+        k: usize,
+        clique: &mut Vec<Node>,
+        candidates: Vec<&Node>,
+        is_wanted: &dyn Fn(&Node) -> bool,
+    ) -> usize {
+        if clique.len() == k {
+            return clique.iter().any(is_wanted) as usize;
+        }
+        let mut count = 0;
+        for (index, node) in candidates.iter().enumerate() {
+            let next_candidates = candidates[index + 1..]
+                .iter()
+                .filter(|candidate| graph.2[*node].contains(**candidate))
+                .copied()
+                .collect::<Vec<&Node>>();
+            if clique.len() + 1 + next_candidates.len() < k {
+                continue;
             }
-        });
+            clique.push((*node).clone());
+            count += extend(graph, k, clique, next_candidates, is_wanted);
+            clique.pop();
+        }
+        count
+    }
 
-    result.into_iter().collect::<Vec<Vec<Node>>>()
+    if k == 0 {
+        return 0;
+    }
+    let is_wanted =
+        |node: &Node| prefixes.is_empty() || prefixes.iter().any(|p| node.starts_with(p));
+    let mut nodes = graph.0.iter().collect::<Vec<&Node>>();
+    nodes.sort();
+    extend(graph, k, &mut vec![], nodes, &is_wanted)
 }
 
-fn remove_recursively(
-    nodes: &HashMap<Node, HashSet<Node>>,
-    min_edges: u32,
-) -> HashMap<Node, HashSet<Node>> {
-    let reduced: HashMap<Node, HashSet<Node>> =
-        HashMap::from_iter(nodes.iter().filter_map(|(n, neighbors)| {
-            if neighbors.len() >= min_edges as usize {
-                Some((n.clone(), neighbors.clone()))
-            } else {
-                None
-            }
-        }));
+// All maximal cliques with Bron-Kerbosch. Every call only branches on the candidates that
+// aren't neighbours of the pivot, as all cliques containing a pivot neighbour also contain
+// the pivot or one of the other branched nodes.
+fn find_maximal_cliques(graph: &Graph) -> Vec<Vec<Node>> {
+    fn bron_kerbosch<'a>(
+        graph: &'a Graph,
+        clique: &mut Vec<&'a Node>,
+        mut candidates: HashSet<&'a Node>,
+        mut excluded: HashSet<&'a Node>,
+        cliques: &mut Vec<Vec<Node>>,
+    ) {
+        if candidates.is_empty() && excluded.is_empty() {
+            let mut maximal_clique = clique.iter().map(|n| (*n).clone()).collect::<Vec<Node>>();
+            maximal_clique.sort();
+            cliques.push(maximal_clique);
+            return;
+        }
+        let pivot = candidates
+            .iter()
+            .chain(excluded.iter())
+            .max_by_key(|node| {
+                let neighbours = &graph.2[**node];
+                candidates
+                    .iter()
+                    .filter(|c| neighbours.contains(**c))
+                    .count()
+            })
+            .copied()
+            .unwrap();
+        let mut branches = candidates
+            .iter()
+            .filter(|node| !graph.2[pivot].contains(**node))
+            .copied()
+            .collect::<Vec<&Node>>();
+        branches.sort();
+
+        for node in branches {
+            let neighbours = &graph.2[node];
+            clique.push(node);
+            bron_kerbosch(
+                graph,
+                clique,
+                candidates
+                    .iter()
+                    .filter(|c| neighbours.contains(**c))
+                    .copied()
+                    .collect(),
+                excluded
+                    .iter()
+                    .filter(|x| neighbours.contains(**x))
+                    .copied()
+                    .collect(),
+                cliques,
+            );
+            clique.pop();
+            candidates.remove(node);
+            excluded.insert(node);
+        }
+    }
 
-    reduced
+    let mut cliques: Vec<Vec<Node>> = vec![];
+    bron_kerbosch(
+        graph,
+        &mut vec![],
+        graph.0.iter().collect(),
+        HashSet::new(),
+        &mut cliques,
+    );
+    cliques.sort();
+    cliques
 }
 
-struct Permutes<T>
-where
-    T: Clone,
-{
-    data: Vec<T>,
-    bases: Vec<i32>,
-    index: i32,
-    n: i32,
-    k: i32,
+// Largest clique, the alphabetically first one if there are several
+fn find_maximum_clique(graph: &Graph) -> Vec<Node> {
+    find_maximal_cliques(graph)
+        .into_iter()
+        .rev()
+        .max_by_key(|clique| clique.len())
+        .unwrap_or_default()
 }
 
-impl<T> Permutes<T>
-where
-    T: Clone,
-{
-    fn new(data: Vec<T>, combinations_length: i32) -> Self {
-        Permutes {
-            data: data.clone(),
-            bases: vec![0; combinations_length as usize],
-            index: 0,
-            n: combinations_length,
-            k: data.len() as i32,
-        }
-    }
+fn sorted_edges(graph: &Graph) -> Vec<(&Node, &Node)> {
+    let mut edges = graph
+        .1
+        .iter()
+        .map(|(a, b)| if a < b { (a, b) } else { (b, a) })
+        .collect::<Vec<(&Node, &Node)>>();
+    edges.sort();
+    edges.dedup();
+    edges
 }
 
-impl<T: Clone> Iterator for Permutes<T> {
-    type Item = Vec<T>;
+// Graphviz representation of the network with the `highlighted` computers and the
+// connections between them drawn in red
+fn export_dot(graph: &Graph, highlighted: &[Node]) -> String {
+    let mut nodes = graph.0.iter().collect::<Vec<&Node>>();
+    nodes.sort();
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.index < 0 {
-            return None;
+    let mut dot = String::from("graph lan {\n");
+    for node in nodes {
+        if highlighted.contains(node) {
+            dot.push_str(&format!("    {} [color=red, style=filled];\n", node));
+        } else {
+            dot.push_str(&format!("    {};\n", node));
         }
-
-        let output = &self.bases.clone();
-
-        self.index = &self.n - 1;
-
-        while self.index >= 0 {
-            self.bases[self.index as usize] += 1;
-
-            if self.index == 0 {
-                if self.bases[self.index as usize] < self.k {
-                    break;
-                }
-            } else {
-                if self.bases[self.index as usize] < self.bases[self.index as usize - 1] {
-                    break;
-                }
-            }
-
-            self.bases[self.index as usize] = 0;
-            self.index -= 1;
+    }
+    for (a, b) in sorted_edges(graph) {
+        if highlighted.contains(a) && highlighted.contains(b) {
+            dot.push_str(&format!("    {} -- {} [color=red, penwidth=2];\n", a, b));
+        } else {
+            dot.push_str(&format!("    {} -- {};\n", a, b));
         }
+    }
+    dot.push_str("}\n");
+    dot
+}
 
-        let mut unique_values = output.clone();
-        unique_values.sort();
-        unique_values.dedup();
+// GraphML representation of the network, the `highlighted` computers and the connections
+// between them have the `clique` attribute set
+fn export_graphml(graph: &Graph, highlighted: &[Node]) -> String {
+    let mut nodes = graph.0.iter().collect::<Vec<&Node>>();
+    nodes.sort();
 
-        if unique_values.len() != output.len() {
-            self.next()
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n  \
+         <key id=\"clique\" for=\"all\" attr.name=\"clique\" attr.type=\"boolean\">\
+         <default>false</default></key>\n  \
+         <graph id=\"lan\" edgedefault=\"undirected\">\n",
+    );
+    for node in nodes {
+        if highlighted.contains(node) {
+            xml.push_str(&format!(
+                "    <node id=\"{}\"><data key=\"clique\">true</data></node>\n",
+                node
+            ));
         } else {
-            let o = output
-                .iter()
-                .map(|x| self.data[*x as usize].clone())
-                .collect::<Vec<T>>();
-            Some(o)
+            xml.push_str(&format!("    <node id=\"{}\"/>\n", node));
+        }
+    }
+    for (a, b) in sorted_edges(graph) {
+        if highlighted.contains(a) && highlighted.contains(b) {
+            xml.push_str(&format!(
+                "    <edge source=\"{}\" target=\"{}\"><data key=\"clique\">true</data></edge>\n",
+                a, b
+            ));
+        } else {
+            xml.push_str(&format!("    <edge source=\"{}\" target=\"{}\"/>\n", a, b));
         }
     }
+    xml.push_str("  </graph>\n</graphml>\n");
+    xml
 }
 
 #[cfg(test)]
@@ -194,4 +282,54 @@ mod tests {
         let solution = solve_part_two(&puzzle);
         assert_eq!(solution, "co,de,ka,ta");
     }
+
+    const EXAMPLE: &str = "kh-tc\nqp-kh\nde-cg\nka-co\nyn-aq\nqp-ub\ncg-tb\nvc-aq\ntb-ka\nwh-tc\nyn-cg\nkh-ub\nta-co\nde-co\ntc-td\ntb-wq\nwh-td\nta-ka\ntd-qp\naq-cg\nwq-ub\nub-vc\nde-ta\nwq-aq\nwq-vc\nwh-yn\nka-de\nkh-ta\nco-tc\nwh-qp\ntb-vc\ntd-yn";
+
+    #[test]
+    fn test_cliques() {
+        let graph = parse_input(EXAMPLE);
+        assert_eq!(solve_part_one(&graph), "7");
+        assert_eq!(solve_part_two(&graph), "co,de,ka,ta");
+        assert_eq!(count_k_cliques(&graph, 3, &[]), 12);
+        assert_eq!(count_k_cliques(&graph, 3, &["t", "y"]), 8);
+        assert_eq!(count_k_cliques(&graph, 4, &[]), 1);
+        assert_eq!(count_k_cliques(&graph, 5, &[]), 0);
+
+        let cliques = find_maximal_cliques(&graph);
+        assert!(cliques.contains(&vec![
+            "co".to_string(),
+            "de".to_string(),
+            "ka".to_string(),
+            "ta".to_string()
+        ]));
+        // Every maximal clique is a clique that can't be extended
+        for clique in &cliques {
+            for (i, a) in clique.iter().enumerate() {
+                assert!(clique[i + 1..].iter().all(|b| graph.2[a].contains(b)));
+            }
+            assert!(!graph
+                .0
+                .iter()
+                .any(|n| !clique.contains(n) && clique.iter().all(|c| graph.2[n].contains(c))));
+        }
+    }
+
+    #[test]
+    fn test_export() {
+        let graph = parse_input("a-b\nb-c\nc-a\nc-d");
+        let clique = find_maximum_clique(&graph);
+        assert_eq!(clique, vec!["a", "b", "c"]);
+
+        let dot = export_dot(&graph, &clique);
+        assert!(dot.starts_with("graph lan {\n    a [color=red, style=filled];\n"));
+        assert!(dot.contains("    d;\n"));
+        assert!(dot.contains("    a -- b [color=red, penwidth=2];\n"));
+        assert!(dot.contains("    c -- d;\n"));
+
+        let graphml = export_graphml(&graph, &clique);
+        assert!(graphml.contains("<node id=\"c\"><data key=\"clique\">true</data></node>"));
+        assert!(graphml.contains("<node id=\"d\"/>"));
+        assert!(graphml.contains("<edge source=\"c\" target=\"d\"/>"));
+        assert_eq!(graphml.matches("<edge ").count(), 4);
+    }
 }
@@ -1,5 +1,6 @@
 use crate::solutions::day24::Operation::{AND, OR, XOR};
 use std::cmp::PartialEq;
+use std::collections::HashMap;
 
 pub fn solve(file_path: &str) -> (String, String) {
     let input = std::fs::read_to_string(file_path).unwrap();
@@ -13,24 +14,29 @@ pub fn solve(file_path: &str) -> (String, String) {
     (part_one.to_string(), part_two.to_string())
 }
 fn solve_part_two(circuit: &mut Circuit) -> String {
-    let mut swaps: Vec<String> = vec![];
-    loop {
-        match circuit.parse_full_adder(0, None) {
-            Ok(_) => {
-                break;
+    let swaps = match circuit.repair(MAX_SWAPS) {
+        Some(swaps) => swaps,
+        None => {
+            return match circuit.parse_adder() {
+                Err(error) => format!("No repair found (bit {}: {})", error.bit, error.message),
+                Ok(()) => "No repair found".to_string(),
             }
-            Err((_, quick_fix)) => match quick_fix {
-                None => break,
-                Some((a, b)) => {
-                    swaps.push(a.clone());
-                    swaps.push(b.clone());
-                    circuit.swap_outputs(a, b);
-                }
-            },
         }
+    };
+    if let Err(fault) = circuit.verify_adder(RANDOM_VERIFICATION_ROUNDS) {
+        return format!("Repair failed ({})", fault);
     }
-    swaps.sort();
-    swaps.join(",")
+
+    let mut cables = swaps
+        .into_iter()
+        .flat_map(|(a, b)| [a, b])
+        .collect::<Vec<CableId>>();
+    cables.sort();
+    cables.join(",")
+}
+
+fn register_name(register: char, index: usize) -> CableId {
+    format!("{}{:0>2}", register, index)
 }
 
 fn parse_circuit(input: &str) -> Circuit {
@@ -107,7 +113,91 @@ enum Operation {
 }
 type CableId = String;
 type SwapSuggestion = (CableId, CableId);
-type ParseError = (String, Option<SwapSuggestion>);
+
+const RANDOM_VERIFICATION_ROUNDS: usize = 256;
+const MAX_SWAPS: usize = 8;
+
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
+enum MatchStage {
+    InputGates,
+    Sum,
+    Output,
+    Carry,
+}
+
+#[derive(Debug, Clone)]
+struct ParseError {
+    bit: usize,
+    stage: MatchStage,
+    message: String,
+    suggestions: Vec<SwapSuggestion>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Counterexample {
+    x: u64,
+    y: u64,
+    expected: u64,
+    actual: u64,
+    failing_bit: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AdderFault {
+    Cyclic(CableId),
+    Mismatch(Counterexample),
+    // Registers are evaluated as u64, so no cable may address bit 64 or above
+    TooWide(usize),
+}
+
+impl std::fmt::Display for AdderFault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AdderFault::Cyclic(cable) => write!(f, "cable {} depends on itself", cable),
+            AdderFault::TooWide(bits) => {
+                write!(f, "{} bits don't fit into 64 bit registers", bits)
+            }
+            AdderFault::Mismatch(c) => write!(
+                f,
+                "bit {} is wrong for {} + {}: expected {} but got {}",
+                c.failing_bit, c.x, c.y, c.expected, c.actual
+            ),
+        }
+    }
+}
+
+// Gates in topological order with cables replaced by indices, so that the circuit can be
+// evaluated many times without searching the gate list.
+struct CompiledCircuit {
+    cable_count: usize,
+    x_cables: Vec<(usize, usize)>,
+    y_cables: Vec<(usize, usize)>,
+    z_cables: Vec<(usize, usize)>,
+    gates: Vec<(Operation, usize, usize, usize)>,
+}
+
+impl CompiledCircuit {
+    fn evaluate(&self, x: u64, y: u64) -> u64 {
+        let mut values = vec![false; self.cable_count];
+        for &(bit, cable) in &self.x_cables {
+            values[cable] = x >> bit & 1 == 1;
+        }
+        for &(bit, cable) in &self.y_cables {
+            values[cable] = y >> bit & 1 == 1;
+        }
+        for (operation, a, b, output) in &self.gates {
+            values[*output] = match operation {
+                AND => values[*a] & values[*b],
+                OR => values[*a] | values[*b],
+                XOR => values[*a] ^ values[*b],
+            };
+        }
+        self.z_cables
+            .iter()
+            .filter(|(_, cable)| values[*cable])
+            .fold(0u64, |acc, (bit, _)| acc | 1 << bit)
+    }
+}
 #[derive(Debug, Clone)]
 struct Gate {
     operation: Operation,
@@ -227,134 +317,204 @@ impl Circuit {
         }
     }
 
-    fn parse_full_adder(&self, index: u8, c_in: Option<CableId>) -> Result<(), ParseError> {
-        if index >= (self.output_length - 1) as u8 {
-            return Ok(());
+    fn parse_adder(&self) -> Result<(), ParseError> {
+        let mut carry = self.parse_half_adder()?;
+        for index in 1..self.input_length() {
+            carry = self.parse_full_adder(index, carry)?;
+        }
+
+        let carry_register = register_name('z', self.input_length());
+        if carry != carry_register {
+            return Err(ParseError {
+                bit: self.input_length(),
+                stage: MatchStage::Output,
+                message: format!("Expected {} as final carry but found {}", carry_register, carry),
+                suggestions: vec![(carry_register, carry)],
+            });
         }
+        Ok(())
+    }
 
-        let x_register = format!("x{:0>2}", index);
-        let y_register = format!("y{:0>2}", index);
+    fn parse_half_adder(&self) -> Result<CableId, ParseError> {
+        let (sum, carry) = self.parse_input_gates(0)?;
+        let output_register = register_name('z', 0);
+        if sum != output_register {
+            return Err(ParseError {
+                bit: 0,
+                stage: MatchStage::Output,
+                message: format!("Expected {} as output but found {}", output_register, sum),
+                suggestions: vec![(output_register, sum)],
+            });
+        }
+        Ok(carry)
+    }
 
-        if index == 0 {
-            let ha = self.parse_half_adder(x_register, y_register);
+    // Matches sum = (x ^ y) ^ c_in and returns the outgoing carry. Accepted carry
+    // decompositions are (x & y) | ((x ^ y) & c_in) and (x & y) | ((x | y) & c_in). Only
+    // the first may merge with an XOR, since x & y and x ^ y are never set at the same time.
+    // With x | y both terms are set for x = y = c_in = 1.
+    fn parse_full_adder(&self, index: usize, c_in: CableId) -> Result<CableId, ParseError> {
+        let (partial_sum, generate) = self.parse_input_gates(index)?;
+
+        let sum = self.find_gate_or_suggest(&partial_sum, &c_in, &[XOR], index, MatchStage::Sum)?;
+        let output_register = register_name('z', index);
+        if sum.output != output_register {
+            return Err(ParseError {
+                bit: index,
+                stage: MatchStage::Output,
+                message: format!("Expected {} as output but found {}", output_register, sum.output),
+                suggestions: vec![(output_register, sum.output.clone())],
+            });
+        }
 
-            if ha.is_err() {
-                return Err(ha.unwrap_err());
+        let x_register = register_name('x', index);
+        let y_register = register_name('y', index);
+        let propagate_candidates = [
+            Some((partial_sum.clone(), &[OR, XOR][..])),
+            self.get_gate_by_inputs_and_op(&x_register, &y_register, OR)
+                .map(|gate| (gate.output.clone(), &[OR][..])),
+        ];
+
+        let mut suggestions: Vec<SwapSuggestion> = vec![];
+        for (propagate, merge_operations) in propagate_candidates.into_iter().flatten() {
+            let carried =
+                match self.find_gate_or_suggest(&propagate, &c_in, &[AND], index, MatchStage::Carry) {
+                    Ok(gate) => gate,
+                    Err(error) => {
+                        suggestions.extend(error.suggestions);
+                        continue;
+                    }
+                };
+            match self.find_gate_or_suggest(
+                &generate,
+                &carried.output,
+                merge_operations,
+                index,
+                MatchStage::Carry,
+            ) {
+                Ok(gate) => return Ok(gate.output.clone()),
+                Err(error) => suggestions.extend(error.suggestions),
             }
-
-            let (_, ha_c_out) = ha?;
-            return self.parse_full_adder(index + 1, Some(ha_c_out.clone()));
         }
 
-        let c_in = c_in.unwrap();
+        suggestions.sort();
+        suggestions.dedup();
+        Err(ParseError {
+            bit: index,
+            stage: MatchStage::Carry,
+            message: format!("Expected carry logic for {}, {}", generate, c_in),
+            suggestions,
+        })
+    }
 
-        let ha_1 = self.parse_half_adder(x_register, y_register);
-        if ha_1.is_err() {
-            return Err(ha_1.unwrap_err());
+    // The gates fed directly by the input registers can't be repaired by swapping outputs
+    fn parse_input_gates(&self, index: usize) -> Result<(CableId, CableId), ParseError> {
+        let x_register = register_name('x', index);
+        let y_register = register_name('y', index);
+        let xor = self.get_gate_by_inputs_and_op(&x_register, &y_register, XOR);
+        let and = self.get_gate_by_inputs_and_op(&x_register, &y_register, AND);
+        match (xor, and) {
+            (Some(xor), Some(and)) => Ok((xor.output.clone(), and.output.clone())),
+            _ => Err(ParseError {
+                bit: index,
+                stage: MatchStage::InputGates,
+                message: format!("Expected XOR/AND gate for {} {}", x_register, y_register),
+                suggestions: vec![],
+            }),
         }
-        let (result_ha_1, carry_ha_1) = ha_1?;
+    }
 
-        let ha_2 = self.parse_half_adder(result_ha_1.clone(), c_in.clone());
-        if ha_2.is_err() {
-            return Err(ha_2.unwrap_err());
+    // Looks for a gate combining `a` and `b`. If there is none, every gate of the same kind
+    // sharing one of the inputs yields a suggestion to swap its other input with the missing one.
+    fn find_gate_or_suggest(
+        &self,
+        a: &CableId,
+        b: &CableId,
+        operations: &[Operation],
+        index: usize,
+        stage: MatchStage,
+    ) -> Result<&Gate, ParseError> {
+        if let Some(gate) = operations
+            .iter()
+            .find_map(|op| self.get_gate_by_inputs_and_op(a, b, op.clone()))
+        {
+            return Ok(gate);
         }
-        let (result_ha_2, carry_ha_2) = ha_2?;
 
-        // Check output
-        let output_register_index = format!("z{:0>2}", index);
-        if result_ha_2 != output_register_index {
-            return Err((
-                format!(
-                    "Expected {} as output but found {}",
-                    output_register_index, result_ha_2
-                ),
-                Some((output_register_index, result_ha_2)),
-            ));
+        let mut suggestions: Vec<SwapSuggestion> = vec![];
+        for gate in self.gates.iter().filter(|g| operations.contains(&g.operation)) {
+            for (present, missing) in [(a, b), (b, a)] {
+                let other = if gate.input_a == *present {
+                    &gate.input_b
+                } else if gate.input_b == *present {
+                    &gate.input_a
+                } else {
+                    continue;
+                };
+                if other != missing && self.is_gate_output(other) && self.is_gate_output(missing) {
+                    suggestions.push((other.clone(), missing.clone()));
+                }
+            }
         }
+        suggestions.sort();
+        suggestions.dedup();
+
+        Err(ParseError {
+            bit: index,
+            stage,
+            message: format!("Expected {:?} gate for {} {}", operations, a, b),
+            suggestions,
+        })
+    }
 
-        // Check carry
-        let c_out = self.get_gate_by_inputs_and_op(&carry_ha_1, &carry_ha_2, OR);
-        if c_out.is_none() {
-            let one_input_match = self
-                .gates
-                .iter()
-                .find(|gate| gate.input_a == carry_ha_1 || gate.input_b == carry_ha_2)
-                .map(|gate| {
-                    if carry_ha_1 == gate.input_a {
-                        return (gate.input_b.clone(), carry_ha_2.clone());
-                    }
-                    if carry_ha_1 == gate.input_b {
-                        return (gate.input_a.clone(), carry_ha_2.clone());
-                    }
-                    if carry_ha_2 == gate.input_a {
-                        return (gate.input_b.clone(), carry_ha_1.clone());
-                    }
-                    if carry_ha_2 == gate.input_b {
-                        return (gate.input_a.clone(), carry_ha_1.clone());
-                    }
-                    panic!("Unknown gate found");
-                });
-
-            return match one_input_match {
-                Some((alt, m)) => Err((
-                    format!("Expected or gate for {}, {}", carry_ha_1, carry_ha_2),
-                    Some((alt, m.clone())),
-                )),
-                None => Err((
-                    format!("Expected or gate for {}, {}", carry_ha_1, carry_ha_2),
-                    None,
-                )),
-            };
-        }
-        let c_out = c_out.unwrap();
+    fn is_gate_output(&self, cable: &CableId) -> bool {
+        self.gates.iter().any(|gate| gate.output == *cable)
+    }
 
-        self.parse_full_adder(index + 1, Some(c_out.output.clone()))
+    // Iterative deepening over the number of swaps. A suggested swap is only followed if the
+    // matcher gets further than before, otherwise it's undone and the next candidate is tried.
+    fn repair(&mut self, max_swaps: usize) -> Option<Vec<SwapSuggestion>> {
+        for swap_limit in 0..=max_swaps {
+            let mut swaps: Vec<SwapSuggestion> = vec![];
+            if self.repair_recursively(&mut swaps, swap_limit) {
+                return Some(swaps);
+            }
+        }
+        None
     }
 
-    fn parse_half_adder(&self, x: CableId, y: CableId) -> Result<(CableId, CableId), ParseError> {
-        // Check AND Gate
-        let and = self.get_gate_by_inputs_and_op(&x, &y, AND);
-        let xor = self.get_gate_by_inputs_and_op(&x, &y, XOR);
+    fn repair_recursively(&mut self, swaps: &mut Vec<SwapSuggestion>, swap_limit: usize) -> bool {
+        let error = match self.parse_adder() {
+            Ok(()) => return self.verify_adder(RANDOM_VERIFICATION_ROUNDS).is_ok(),
+            Err(error) => error,
+        };
+        if swaps.len() == swap_limit {
+            return false;
+        }
 
-        // 'XOR' and 'AND' have the same inputs
-        if and.is_none() || xor.is_none() {
-            let one_input_match = self
-                .gates
+        for (a, b) in error.suggestions.iter() {
+            let already_swapped = swaps
                 .iter()
-                .find(|gate| gate.input_a == x || gate.input_b == y)
-                .map(|gate| {
-                    if x == gate.input_a {
-                        return (gate.input_b.clone(), y.clone());
-                    }
-                    if x == gate.input_b {
-                        return (gate.input_a.clone(), y.clone());
-                    }
-                    if y == gate.input_a {
-                        return (gate.input_b.clone(), x.clone());
-                    }
-                    if y == gate.input_b {
-                        return (gate.input_a.clone(), x.clone());
-                    }
-                    panic!("Unknown gate found")
-                });
-
-            return match one_input_match {
-                Some((alt, m)) => Err((
-                    format!("Expected XOR/AND gate for {} {} [Fix available]", x, y).to_string(),
-                    Some((alt.clone(), m.clone())),
-                )),
-                None => Err((
-                    format!("Expected XOR/AND gate for {} {}", x, y).to_string(),
-                    None,
-                )),
+                .any(|(c, d)| [c, d].contains(&a) || [c, d].contains(&b));
+            if already_swapped {
+                continue;
+            }
+
+            self.swap_outputs(a.clone(), b.clone());
+            let progressed = match self.parse_adder() {
+                Ok(()) => true,
+                Err(next) => (next.bit, next.stage) > (error.bit, error.stage),
             };
+            if progressed {
+                swaps.push((a.clone(), b.clone()));
+                if self.repair_recursively(swaps, swap_limit) {
+                    return true;
+                }
+                swaps.pop();
+            }
+            self.swap_outputs(a.clone(), b.clone());
         }
-        let and = and.unwrap();
-        let xor = xor.unwrap();
-
-        let output = xor.output.clone();
-        let c_out = and.output.clone();
-        Ok((output, c_out))
+        false
     }
 
     fn get_gate_by_inputs_and_op(
@@ -395,4 +555,294 @@ impl Circuit {
             })
             .collect::<Vec<Gate>>();
     }
+
+    fn input_length(&self) -> usize {
+        self.output_length - 1
+    }
+
+    fn compile(&self) -> Result<CompiledCircuit, AdderFault> {
+        let mut cable_index: HashMap<&str, usize> = HashMap::new();
+        for gate in &self.gates {
+            for cable in [&gate.input_a, &gate.input_b, &gate.output] {
+                let next_index = cable_index.len();
+                cable_index.entry(cable.as_str()).or_insert(next_index);
+            }
+        }
+        let driver: HashMap<&str, usize> = self
+            .gates
+            .iter()
+            .enumerate()
+            .map(|(i, gate)| (gate.output.as_str(), i))
+            .collect();
+
+        // Iterative depth-first topological sort; 1 = in progress, 2 = done
+        let mut state = vec![0u8; self.gates.len()];
+        let mut order: Vec<usize> = Vec::with_capacity(self.gates.len());
+        for root in 0..self.gates.len() {
+            let mut stack = vec![(root, false)];
+            while let Some((gate_index, expanded)) = stack.pop() {
+                if expanded {
+                    state[gate_index] = 2;
+                    order.push(gate_index);
+                    continue;
+                }
+                match state[gate_index] {
+                    2 => continue,
+                    1 => return Err(AdderFault::Cyclic(self.gates[gate_index].output.clone())),
+                    _ => {}
+                }
+                state[gate_index] = 1;
+                stack.push((gate_index, true));
+                let gate = &self.gates[gate_index];
+                for input in [&gate.input_a, &gate.input_b] {
+                    if let Some(&dependency) = driver.get(input.as_str()) {
+                        match state[dependency] {
+                            0 => stack.push((dependency, false)),
+                            1 => return Err(AdderFault::Cyclic(input.clone())),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+
+        let register_cables = |prefix: char| -> Vec<(usize, usize)> {
+            cable_index
+                .iter()
+                .filter(|(cable, _)| cable.starts_with(prefix))
+                .filter_map(|(cable, &i)| cable[1..].parse::<usize>().ok().map(|bit| (bit, i)))
+                .collect()
+        };
+
+        let (x_cables, y_cables, z_cables) = (
+            register_cables('x'),
+            register_cables('y'),
+            register_cables('z'),
+        );
+        let bits = x_cables
+            .iter()
+            .chain(&y_cables)
+            .chain(&z_cables)
+            .map(|(bit, _)| bit + 1)
+            .chain([self.output_length])
+            .max()
+            .unwrap_or(0);
+        if bits > u64::BITS as usize {
+            return Err(AdderFault::TooWide(bits));
+        }
+
+        Ok(CompiledCircuit {
+            cable_count: cable_index.len(),
+            x_cables,
+            y_cables,
+            z_cables,
+            gates: order
+                .iter()
+                .map(|&i| {
+                    let gate = &self.gates[i];
+                    (
+                        gate.operation.clone(),
+                        cable_index[gate.input_a.as_str()],
+                        cable_index[gate.input_b.as_str()],
+                        cable_index[gate.output.as_str()],
+                    )
+                })
+                .collect(),
+        })
+    }
+
+    // Checks that the circuit computes x + y. Every bit slice is tested exhaustively
+    // (both input bits and the incoming carry), followed by edge cases and `random_rounds`
+    // pseudo-random inputs. Returns the first counterexample found.
+    fn verify_adder(&self, random_rounds: usize) -> Result<(), AdderFault> {
+        let compiled = self.compile()?;
+        let mask = |bits: usize| 1u64.checked_shl(bits as u32).map_or(u64::MAX, |m| m - 1);
+        let input_mask = mask(self.input_length());
+        let output_mask = mask(self.output_length);
+
+        let check = |x: u64, y: u64| -> Result<(), AdderFault> {
+            let (x, y) = (x & input_mask, y & input_mask);
+            let expected = x.wrapping_add(y) & output_mask;
+            let actual = compiled.evaluate(x, y);
+            if expected == actual {
+                Ok(())
+            } else {
+                Err(AdderFault::Mismatch(Counterexample {
+                    x,
+                    y,
+                    expected,
+                    actual,
+                    failing_bit: (expected ^ actual).trailing_zeros() as usize,
+                }))
+            }
+        };
+
+        for bit in 0..self.input_length() {
+            for slice in 0..8u64 {
+                let (a, b, carry) = (slice & 1, slice >> 1 & 1, slice >> 2 & 1);
+                if bit == 0 && carry == 1 {
+                    continue;
+                }
+                let carry_in = if carry == 1 { 1 << (bit - 1) } else { 0 };
+                check(a << bit | carry_in, b << bit | carry_in)?;
+            }
+        }
+
+        let edge_cases = [
+            (0, 0),
+            (input_mask, 0),
+            (0, input_mask),
+            (input_mask, 1),
+            (input_mask, input_mask),
+            (0x5555_5555_5555_5555, 0xAAAA_AAAA_AAAA_AAAA),
+            (0xAAAA_AAAA_AAAA_AAAA, 0xAAAA_AAAA_AAAA_AAAA),
+        ];
+        for (x, y) in edge_cases {
+            check(x, y)?;
+        }
+
+        let mut seed = 0x2024_1224_u64;
+        let mut next_random = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        for _ in 0..random_rounds {
+            check(next_random(), next_random())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two bit ripple-carry adder: z00 = x00 ^ y00, z01 = x01 ^ y01 ^ c0, z02 = carry
+    const ADDER: &str = "x00: 1
+x01: 0
+y00: 1
+y01: 1
+
+x00 XOR y00 -> z00
+x00 AND y00 -> c00
+x01 XOR y01 -> s01
+x01 AND y01 -> a01
+s01 XOR c00 -> z01
+s01 AND c00 -> b01
+a01 OR b01 -> z02
+";
+
+    #[test]
+    fn test_verify_correct_adder() {
+        let circuit = parse_circuit(ADDER);
+        assert_eq!(circuit.evaluate(circuit.default_x, circuit.default_y), Ok(4));
+        assert_eq!(circuit.verify_adder(64), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_swapped_adder() {
+        let mut circuit = parse_circuit(ADDER);
+        circuit.swap_outputs("z01".to_string(), "b01".to_string());
+        match circuit.verify_adder(64) {
+            Err(AdderFault::Mismatch(c)) => {
+                assert_eq!(c.failing_bit, 1);
+                assert_eq!((c.x + c.y) & 0b111, c.expected);
+                assert_ne!(c.expected, c.actual);
+            }
+            other => panic!("Expected mismatch but got {:?}", other),
+        }
+
+        circuit.swap_outputs("s01".to_string(), "b01".to_string());
+        assert!(matches!(circuit.verify_adder(64), Err(AdderFault::Cyclic(_))));
+    }
+
+    // Ripple-carry adder where the propagate term of the carry is either x ^ y or x | y
+    fn generate_adder(bits: usize, or_propagate: bool) -> String {
+        let mut lines: Vec<String> = (0..bits)
+            .flat_map(|i| [format!("x{:0>2}: 0", i), format!("y{:0>2}: 0", i)])
+            .collect();
+        lines.push(String::new());
+        lines.push("x00 XOR y00 -> z00".to_string());
+        lines.push("x00 AND y00 -> cry00".to_string());
+        for i in 1..bits {
+            let carry_out = if i == bits - 1 {
+                format!("z{:0>2}", bits)
+            } else {
+                format!("cry{:0>2}", i)
+            };
+            lines.push(format!("x{i:0>2} XOR y{i:0>2} -> sum{i:0>2}"));
+            lines.push(format!("y{i:0>2} AND x{i:0>2} -> gen{i:0>2}"));
+            lines.push(format!("cry{:0>2} XOR sum{i:0>2} -> z{i:0>2}", i - 1));
+            let propagate = if or_propagate {
+                lines.push(format!("x{i:0>2} OR y{i:0>2} -> prp{i:0>2}"));
+                format!("prp{i:0>2}")
+            } else {
+                format!("sum{i:0>2}")
+            };
+            lines.push(format!("{propagate} AND cry{:0>2} -> and{i:0>2}", i - 1));
+            let merge = if or_propagate { "OR" } else { "XOR" };
+            lines.push(format!("gen{i:0>2} {merge} and{i:0>2} -> {carry_out}"));
+        }
+        lines.join("\n")
+    }
+
+    #[test]
+    fn test_alternative_carry_decompositions() {
+        for or_propagate in [false, true] {
+            let circuit = parse_circuit(&generate_adder(12, or_propagate));
+            assert!(circuit.parse_adder().is_ok());
+            assert_eq!(circuit.verify_adder(64), Ok(()));
+        }
+
+        // x | y overlaps with x & y, so merging both terms with an XOR breaks the carry
+        let broken = generate_adder(12, true).replace("gen05 OR and05", "gen05 XOR and05");
+        let circuit = parse_circuit(&broken);
+        assert!(circuit.verify_adder(64).is_err());
+        let error = circuit.parse_adder().unwrap_err();
+        assert_eq!((error.bit, error.stage), (5, MatchStage::Carry));
+    }
+
+    #[test]
+    fn test_register_width() {
+        // 63 input bits and 64 output bits still fit into a u64
+        let circuit = parse_circuit(&generate_adder(63, false));
+        assert_eq!(circuit.output_length, 64);
+        assert_eq!(circuit.verify_adder(64), Ok(()));
+
+        let circuit = parse_circuit(&generate_adder(64, false));
+        assert_eq!(circuit.verify_adder(64), Err(AdderFault::TooWide(65)));
+    }
+
+    #[test]
+    fn test_repair_more_than_four_swaps() {
+        let normalize = |(a, b): SwapSuggestion| if a < b { (a, b) } else { (b, a) };
+        let expected = [
+            ("z03", "sum03"),
+            ("gen05", "sum06"),
+            ("z07", "cry07"),
+            ("cry09", "z10"),
+            ("sum12", "gen12"),
+        ];
+        for or_propagate in [false, true] {
+            let mut circuit = parse_circuit(&generate_adder(16, or_propagate));
+            for (a, b) in &expected {
+                circuit.swap_outputs(a.to_string(), b.to_string());
+            }
+            assert!(circuit.verify_adder(64).is_err());
+
+            let swaps = circuit.repair(MAX_SWAPS).unwrap();
+            assert_eq!(circuit.verify_adder(64), Ok(()));
+
+            let mut swaps = swaps.into_iter().map(normalize).collect::<Vec<_>>();
+            swaps.sort();
+            let mut expected = expected
+                .iter()
+                .map(|(a, b)| normalize((a.to_string(), b.to_string())))
+                .collect::<Vec<_>>();
+            expected.sort();
+            assert_eq!(swaps, expected);
+        }
+    }
 }
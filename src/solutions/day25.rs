@@ -1,81 +1,327 @@
+use std::collections::HashMap;
 use std::iter::zip;
 
 pub fn solve(input_file_path: &str) -> (String, String) {
     let input = std::fs::read_to_string(input_file_path).unwrap();
-    let (lock, key) = parse_puzzle(&input);
-    let part_one = solve_part_one(&lock, &key);
-    (part_one.to_string(), "".to_string())
+    let part_one = match parse_puzzle(&input) {
+        Ok(schematics) => solve_part_one(&schematics).to_string(),
+        Err(message) => message,
+    };
+    (part_one, "".to_string())
+}
+
+// Draws which keys fit into which locks, large inputs only get the count
+pub fn explain(input_file_path: &str) -> Result<String, String> {
+    const DRAWN_SCHEMATICS: usize = 40;
+    let input = std::fs::read_to_string(input_file_path).map_err(|e| e.to_string())?;
+    let schematics = parse_puzzle(&input)?;
+
+    let mut lines: Vec<String> = vec![format!(
+        "{} locks, {} keys, {} pairs fit",
+        schematics.locks.len(),
+        schematics.keys.len(),
+        solve_part_one(&schematics)
+    )];
+    if schematics.locks.len().max(schematics.keys.len()) <= DRAWN_SCHEMATICS {
+        lines.push(render_fit_matrix(&schematics));
+    }
+    Ok(lines.join("\n"))
 }
 
 type Lock = Vec<u8>;
 type Key = Vec<u8>;
 
-fn parse_puzzle(input: &str) -> (Vec<Lock>, Vec<Key>) {
-    let key_width = input.lines().nth(0).unwrap().len();
-
-    let mut keys: Vec<Vec<u8>> = Vec::new();
-    let mut locks: Vec<Vec<u8>> = Vec::new();
-
-    let mut buff = String::new();
-    let mut iter = input.lines().peekable();
-    while let Some(line) = iter.next() {
-        buff.push_str(line);
-        if line.trim().is_empty() || iter.peek().is_none() {
-            // Parse locks
-            if buff.starts_with("#") {
-                let mut lock = vec![0u8; key_width];
-                buff.chars()
-                    .skip(key_width)
-                    .enumerate()
-                    .for_each(|(i, x)| match x {
-                        '#' => lock[i % key_width] += 1,
-                        _ => {}
-                    });
-                locks.push(lock)
+#[derive(Debug)]
+struct Schematics {
+    width: usize,
+    // Rows between the top and the bottom row, the sum of a lock and a key pin must fit in
+    space: u8,
+    locks: Vec<Lock>,
+    keys: Vec<Key>,
+}
+
+// Schematics are blocks of '#' and '.' separated by empty lines, all of the same size. A lock
+// has a filled top row and pins hanging down, a key has a filled bottom row and pins going up.
+fn parse_puzzle(input: &str) -> Result<Schematics, String> {
+    let mut blocks: Vec<Vec<(usize, &str)>> = vec![vec![]];
+    for (index, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            blocks.push(vec![]);
+        } else {
+            blocks
+                .last_mut()
+                .unwrap()
+                .push((index + 1, line.trim_end()));
+        }
+    }
+    blocks.retain(|block| !block.is_empty());
+    let first_block = blocks.first().ok_or("No schematics found")?;
+    let width = first_block[0].1.len();
+    let height = first_block.len();
+    if width == 0 || height < 2 {
+        return Err(format!(
+            "Line {}: schematics need at least two rows",
+            first_block[0].0
+        ));
+    }
+
+    let mut schematics = Schematics {
+        width,
+        space: u8::try_from(height - 2).map_err(|_| "Schematics are too high".to_string())?,
+        locks: vec![],
+        keys: vec![],
+    };
+    for block in blocks {
+        let (first_line, _) = block[0];
+        if block.len() != height {
+            return Err(format!(
+                "Line {}: expected a schematic with {} rows, found {}",
+                first_line,
+                height,
+                block.len()
+            ));
+        }
+        for (line_number, row) in &block {
+            if row.len() != width {
+                return Err(format!(
+                    "Line {}: expected {} columns, found {}",
+                    line_number,
+                    width,
+                    row.len()
+                ));
             }
+            if let Some(c) = row.chars().find(|c| *c != '#' && *c != '.') {
+                return Err(format!(
+                    "Line {}: unexpected character '{}'",
+                    line_number, c
+                ));
+            }
+        }
 
-            // Parse keys
-            if buff.starts_with('.') {
-                let mut key = vec![0u8; key_width];
-                buff.chars()
-                    .skip(key_width)
-                    .enumerate()
-                    .for_each(|(i, x)| match x {
-                        '#' => key[i % key_width] += 1,
-                        _ => {}
-                    });
-                key = key.iter().map(|v| v - 1).collect();
-                keys.push(key);
+        let is_lock = block[0].1.chars().all(|c| c == '#');
+        let is_key = block[height - 1].1.chars().all(|c| c == '#');
+        if is_lock == is_key {
+            return Err(format!(
+                "Line {}: either the top or the bottom row has to be filled",
+                first_line
+            ));
+        }
+
+        // Rows from the base of the pins to their tips, each pin has to be a solid column
+        let mut rows: Vec<(usize, &str)> = block.clone();
+        if is_key {
+            rows.reverse();
+        }
+        let mut pins: Vec<u8> = vec![0; width];
+        let mut ended: Vec<bool> = vec![false; width];
+        for (line_number, row) in &rows[1..] {
+            for (column, cell) in row.bytes().enumerate() {
+                match (cell, ended[column]) {
+                    (b'#', false) => pins[column] += 1,
+                    (b'#', true) => {
+                        return Err(format!(
+                            "Line {}: pin {} has a gap",
+                            line_number,
+                            column + 1
+                        ))
+                    }
+                    _ => ended[column] = true,
+                }
             }
+        }
+        if let Some(column) = pins.iter().position(|pin| *pin > schematics.space) {
+            return Err(format!(
+                "Line {}: pin {} fills the whole schematic",
+                rows[height - 1].0,
+                column + 1
+            ));
+        }
 
-            buff.clear();
+        if is_lock {
+            schematics.locks.push(pins);
+        } else {
+            schematics.keys.push(pins);
         }
     }
-    (locks, keys)
+    Ok(schematics)
+}
+
+// Pins are widened before adding, two pins of a tall schematic can exceed a u8 together
+fn fits(schematics: &Schematics, lock: &Lock, key: &Key) -> bool {
+    zip(key, lock).all(|(a, b)| u16::from(*a) + u16::from(*b) <= u16::from(schematics.space))
+}
+
+fn solve_part_one(schematics: &Schematics) -> u64 {
+    count_fitting_pairs(schematics)
 }
 
-fn solve_part_one(locks: &Vec<Lock>, keys: &Vec<Key>) -> u32 {
-    let mut unique_combos = 0;
-    for key in keys {
-        for lock in locks {
-            let fits = zip(key, lock).map(|(a, b)| *a + *b).all(|l| l <= 5);
-            if fits {
-                unique_combos += 1;
+// Identical locks and keys are counted only once and the keys are sorted by their first pin,
+// so every lock only checks the keys that are short enough in the first column
+fn count_fitting_pairs(schematics: &Schematics) -> u64 {
+    let bucket = |schematics: &[Vec<u8>]| -> Vec<(Vec<u8>, u64)> {
+        let mut counts: HashMap<&Vec<u8>, u64> = HashMap::new();
+        for schematic in schematics {
+            *counts.entry(schematic).or_default() += 1;
+        }
+        let mut buckets = counts
+            .into_iter()
+            .map(|(pins, count)| (pins.clone(), count))
+            .collect::<Vec<(Vec<u8>, u64)>>();
+        buckets.sort();
+        buckets
+    };
+    let locks = bucket(&schematics.locks);
+    let keys = bucket(&schematics.keys);
+
+    let mut fitting_pairs = 0;
+    for (lock, lock_count) in &locks {
+        let max_first_pin = schematics.space - lock[0];
+        let candidates = keys.partition_point(|(key, _)| key[0] <= max_first_pin);
+        for (key, key_count) in &keys[..candidates] {
+            if fits(schematics, lock, key) {
+                fitting_pairs += lock_count * key_count;
             }
         }
     }
-    unique_combos
+    fitting_pairs
+}
+
+// Every row is a lock and every column a key
+fn fit_matrix(schematics: &Schematics) -> Vec<Vec<bool>> {
+    schematics
+        .locks
+        .iter()
+        .map(|lock| {
+            schematics
+                .keys
+                .iter()
+                .map(|key| fits(schematics, lock, key))
+                .collect()
+        })
+        .collect()
+}
+
+fn render_fit_matrix(schematics: &Schematics) -> String {
+    let format_pins = |pins: &[u8]| {
+        pins.iter()
+            .map(|pin| pin.to_string())
+            .collect::<Vec<String>>()
+            .join(",")
+    };
+    let label_width = 2 * schematics.width - 1;
+    let mut lines: Vec<String> = vec![];
+    for (index, key) in schematics.keys.iter().enumerate() {
+        lines.push(format!(
+            "{: >width$}  {}key {}: {}",
+            "",
+            "| ".repeat(index),
+            index + 1,
+            format_pins(key),
+            width = label_width
+        ));
+    }
+    for (lock, fitting) in zip(&schematics.locks, fit_matrix(schematics)) {
+        let cells = fitting
+            .iter()
+            .map(|fits| if *fits { "X" } else { "." })
+            .collect::<Vec<&str>>()
+            .join(" ");
+        lines.push(format!("{}  {}", format_pins(lock), cells));
+    }
+    lines.join("\n")
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::solutions::day25::{parse_puzzle, solve_part_one};
+    use super::*;
 
     #[test]
     fn test_parse_puzzle() {
         let input = std::fs::read_to_string("./resources/day25/example.txt").unwrap();
-        let (lock, key) = parse_puzzle(&input);
-        let part_one = solve_part_one(&lock, &key);
+        let schematics = parse_puzzle(&input).unwrap();
+        let part_one = solve_part_one(&schematics);
         assert_eq!(part_one, 3);
     }
+
+    const EXAMPLE: &str = "#####\n.####\n.####\n.####\n.#.#.\n.#...\n.....\n\n#####\n##.##\n.#.##\n...##\n...#.\n...#.\n.....\n\n.....\n#....\n#....\n#...#\n#.#.#\n#.###\n#####\n\n.....\n.....\n#.#..\n###..\n###.#\n###.#\n#####\n\n.....\n.....\n.....\n#....\n#.#..\n#.#.#\n#####";
+
+    #[test]
+    fn test_schematics() {
+        let schematics = parse_puzzle(EXAMPLE).unwrap();
+        assert_eq!(
+            schematics.locks,
+            vec![vec![0, 5, 3, 4, 3], vec![1, 2, 0, 5, 3]]
+        );
+        assert_eq!(schematics.keys[0], vec![5, 0, 2, 1, 3]);
+        assert_eq!(solve_part_one(&schematics), 3);
+        assert_eq!(
+            render_fit_matrix(&schematics),
+            "           key 1: 5,0,2,1,3\n\
+             \x20          | key 2: 4,3,4,0,2\n\
+             \x20          | | key 3: 3,0,2,0,1\n\
+             0,5,3,4,3  . . X\n\
+             1,2,0,5,3  . X X"
+        );
+
+        // Other sizes and duplicates
+        let input = "###\n#..\n...\n\n...\n..#\n###\n\n...\n..#\n###\n\n...\n#..\n###";
+        let schematics = parse_puzzle(input).unwrap();
+        assert_eq!(schematics.space, 1);
+        assert_eq!(solve_part_one(&schematics), 2);
+        assert_eq!(fit_matrix(&schematics), vec![vec![true, true, false]]);
+    }
+
+    #[test]
+    fn test_tall_schematics() {
+        // 200 rows between top and bottom, pins above 127 must not wrap around when added
+        let schematic = |pin: usize, is_lock: bool| {
+            let mut rows = vec!["#"; pin + 1];
+            rows.extend(vec!["."; 201 - pin]);
+            if !is_lock {
+                rows.reverse();
+            }
+            rows.join("\n")
+        };
+        let input = [
+            schematic(150, true),
+            schematic(50, false),
+            schematic(51, false),
+            schematic(150, false),
+        ]
+        .join("\n\n");
+        let schematics = parse_puzzle(&input).unwrap();
+        assert_eq!(schematics.space, 200);
+        assert_eq!(schematics.locks, vec![vec![150]]);
+        assert_eq!(schematics.keys, vec![vec![50], vec![51], vec![150]]);
+        assert_eq!(fit_matrix(&schematics), vec![vec![true, false, false]]);
+        assert_eq!(solve_part_one(&schematics), 1);
+    }
+
+    #[test]
+    fn test_malformed_schematics() {
+        let error = |input: &str| parse_puzzle(input).unwrap_err();
+        assert_eq!(error(""), "No schematics found");
+        assert_eq!(
+            error("###\n#..\n...\n\n...\n..\n###"),
+            "Line 6: expected 3 columns, found 2"
+        );
+        assert_eq!(
+            error("###\n#..\n...\n\n...\n###"),
+            "Line 5: expected a schematic with 3 rows, found 2"
+        );
+        assert_eq!(error("###\n#x.\n..."), "Line 2: unexpected character 'x'");
+        assert_eq!(
+            error("###\n#..\n###"),
+            "Line 1: either the top or the bottom row has to be filled"
+        );
+        assert_eq!(error("###\n...\n#..\n..."), "Line 3: pin 1 has a gap");
+        assert_eq!(
+            error("###\n#..\n#.."),
+            "Line 3: pin 1 fills the whole schematic"
+        );
+        assert_eq!(
+            error("..#\n..#\n..#\n###"),
+            "Line 1: pin 3 fills the whole schematic"
+        );
+    }
 }